@@ -0,0 +1,200 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Command line switches for days that use the result cache. Not a doc comment: clap would
+// use it as the `about` text of every program that flattens these in.
+#[derive(clap::Args, Debug, Default)]
+pub struct CacheArgs {
+    /// Don't read or write cached results
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Recompute results and overwrite the cached values
+    #[arg(long, conflicts_with = "no_cache")]
+    pub refresh: bool,
+}
+
+/// On-disk cache of puzzle answers.
+///
+/// Entries are keyed by day, part, a hash of the input file, solver parameters and a solver
+/// version. Bumping the version makes older entries unreachable and they are removed the
+/// next time that part is stored. The directory is `$AOC_CACHE_DIR`, or `.aoc-cache` in the
+/// current directory.
+pub struct Cache {
+    dir: Option<PathBuf>,
+    refresh: bool,
+    day: String,
+    version: u32,
+    input_hash: u64,
+}
+
+impl Cache {
+    /// Opens the cache for `day`'s answers on `input`.
+    ///
+    /// Bump `version` whenever a change to the solver could alter its answers, so answers
+    /// cached by the old solver are no longer used.
+    pub fn open(day: &str, version: u32, input: &str, args: &CacheArgs) -> io::Result<Cache> {
+        let (dir, input_hash) = if args.no_cache {
+            (None, 0)
+        } else {
+            let dir =
+                env::var_os("AOC_CACHE_DIR").map_or_else(|| ".aoc-cache".into(), PathBuf::from);
+            (Some(dir), fnv1a(&fs::read(input)?))
+        };
+
+        Ok(Cache {
+            dir,
+            refresh: args.refresh,
+            day: day.to_owned(),
+            version,
+            input_hash,
+        })
+    }
+
+    /// Returns the cached answer for `part`, or computes and stores it.
+    ///
+    /// `params` should describe anything besides the input that changes the answer.
+    pub fn get_or_insert_with<T, F>(&self, part: u32, params: &str, f: F) -> T
+    where
+        T: Display + FromStr,
+        F: FnOnce() -> T,
+    {
+        let Some(dir) = &self.dir else {
+            return f();
+        };
+
+        let key = format!(
+            "day={} part={} version={} input={:016x} params={}",
+            self.day, part, self.version, self.input_hash, params
+        );
+        let path = dir.join(format!(
+            "{}-p{}-v{}-{:016x}",
+            self.day,
+            part,
+            self.version,
+            fnv1a(key.as_bytes())
+        ));
+
+        if !self.refresh
+            && let Some(value) = load(&path, &key)
+        {
+            return value;
+        }
+
+        let value = f();
+        if let Err(e) = self.store(dir, &path, part, &key, &value) {
+            eprintln!("Warning: unable to cache result: {}", e);
+        }
+        value
+    }
+
+    fn store<T: Display>(
+        &self,
+        dir: &Path,
+        path: &Path,
+        part: u32,
+        key: &str,
+        value: &T,
+    ) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        // Drop entries written by other solver versions. They can't be read back anyway, so
+        // failing to remove one shouldn't stop the new entry being written:
+        let prefix = format!("{}-p{}-v", self.day, part);
+        let current = format!("{}{}-", prefix, self.version);
+        for entry in fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with(&prefix)
+                && !name.starts_with(&current)
+                && let Err(e) = fs::remove_file(entry.path())
+            {
+                eprintln!(
+                    "Warning: unable to remove stale cache entry {}: {}",
+                    name, e
+                );
+            }
+        }
+
+        fs::write(path, format!("{}\n{}", key, value))
+    }
+}
+
+fn load<T: FromStr>(path: &Path, key: &str) -> Option<T> {
+    let contents = fs::read_to_string(path).ok()?;
+    let (stored_key, value) = contents.split_once('\n')?;
+    if stored_key != key {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// FNV-1a; unlike `DefaultHasher` it is stable across Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn test_cache(name: &str, version: u32, refresh: bool) -> Cache {
+        Cache {
+            dir: Some(env::temp_dir().join(format!("aoc-cache-test-{}", name))),
+            refresh,
+            day: "d0".to_owned(),
+            version,
+            input_hash: fnv1a(b"1 2 3"),
+        }
+    }
+
+    #[test]
+    fn test_cache_hit_and_invalidation() {
+        let _ = fs::remove_dir_all(test_cache("hit", 1, false).dir.unwrap());
+        let calls = Cell::new(0);
+        let compute = || {
+            calls.set(calls.get() + 1);
+            42u64
+        };
+
+        let cache = test_cache("hit", 1, false);
+        assert_eq!(cache.get_or_insert_with(1, "", compute), 42);
+        assert_eq!(cache.get_or_insert_with(1, "", compute), 42);
+        assert_eq!(calls.get(), 1);
+
+        // Different parameters are a different entry:
+        cache.get_or_insert_with(1, "steps=10", compute);
+        assert_eq!(calls.get(), 2);
+
+        test_cache("hit", 1, true).get_or_insert_with(1, "", compute);
+        assert_eq!(calls.get(), 3);
+
+        let bumped = test_cache("hit", 2, false);
+        bumped.get_or_insert_with(1, "", compute);
+        assert_eq!(calls.get(), 4);
+        let stale = fs::read_dir(bumped.dir.as_ref().unwrap())
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .contains("-v1-")
+            })
+            .count();
+        assert_eq!(stale, 0);
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
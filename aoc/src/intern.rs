@@ -0,0 +1,168 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A name representation that can be stored in an `Interner`.
+///
+/// Keys must borrow as `str` (and hash identically) so lookups by name never allocate.
+pub trait Key: Eq + Hash + Borrow<str> + Sized {
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+impl Key for String {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(name.to_owned())
+    }
+}
+
+/// A fixed-width name such as `kh` or `x00`, stored inline without allocating.
+#[derive(Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Debug)]
+pub struct Packed<const N: usize>([u8; N]);
+
+impl<const N: usize> Packed<N> {
+    pub fn as_str(&self) -> &str {
+        // Only ever built from a `&str` of exactly N bytes:
+        std::str::from_utf8(&self.0).expect("packed key is valid UTF-8")
+    }
+}
+
+impl<const N: usize> Key for Packed<N> {
+    fn from_name(name: &str) -> Option<Self> {
+        name.as_bytes().try_into().ok().map(Packed)
+    }
+}
+
+impl<const N: usize> Borrow<str> for Packed<N> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> Hash for Packed<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+/// Assigns dense ids (`0..len`) to names in first-seen order.
+#[derive(Debug)]
+pub struct Interner<K = String> {
+    ids: HashMap<K, usize>,
+    names: Vec<K>,
+}
+
+impl<K: Key + Clone> Interner<K> {
+    pub fn new() -> Self {
+        Interner {
+            ids: HashMap::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Returns the id for `name`, assigning the next one if it is new.
+    ///
+    /// Returns `None` if `name` can't be represented as a `K`.
+    pub fn intern(&mut self, name: &str) -> Option<usize> {
+        if let Some(&id) = self.ids.get(name) {
+            return Some(id);
+        }
+        let key = K::from_name(name)?;
+        let id = self.names.len();
+        self.names.push(key.clone());
+        self.ids.insert(key, id);
+        Some(id)
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn resolve(&self, id: usize) -> &str {
+        self.names[id].borrow()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Converts into an immutable table; ids are preserved.
+    pub fn freeze(self) -> Symbols<K> {
+        Symbols {
+            ids: self.ids,
+            names: self.names.into_boxed_slice(),
+        }
+    }
+}
+
+impl<K: Key + Clone> Default for Interner<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An immutable id <-> name table produced by `Interner::freeze`.
+#[derive(Debug)]
+pub struct Symbols<K = String> {
+    ids: HashMap<K, usize>,
+    names: Box<[K]>,
+}
+
+impl<K: Key> Symbols<K> {
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn resolve(&self, id: usize) -> &str {
+        self.names[id].borrow()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Iterates over `(id, name)` pairs in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.names.iter().map(|k| k.borrow()).enumerate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_dense_ids() {
+        let mut names = Interner::<String>::new();
+        assert_eq!(names.intern("kh"), Some(0));
+        assert_eq!(names.intern("tc"), Some(1));
+        assert_eq!(names.intern("kh"), Some(0));
+        assert_eq!(names.get("qp"), None);
+        assert_eq!(names.resolve(1), "tc");
+        assert_eq!(names.len(), 2);
+    }
+
+    #[test]
+    fn test_packed() {
+        let mut names = Interner::<Packed<3>>::new();
+        assert_eq!(names.intern("x00"), Some(0));
+        assert_eq!(names.intern("z12"), Some(1));
+        assert_eq!(names.intern("x0"), None);
+        assert_eq!(names.intern("x000"), None);
+
+        let symbols = names.freeze();
+        assert_eq!(symbols.get("z12"), Some(1));
+        assert_eq!(symbols.resolve(0), "x00");
+        assert_eq!(
+            symbols.iter().collect::<Vec<_>>(),
+            vec![(0, "x00"), (1, "z12")]
+        );
+    }
+}
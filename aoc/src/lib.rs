@@ -1,3 +1,7 @@
+pub mod cache;
+pub mod intern;
+pub mod num;
+
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
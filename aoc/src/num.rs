@@ -0,0 +1,246 @@
+use std::cmp::Ordering;
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Returns `None` on overflow. `lcm(0, n)` is 0.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y == g == gcd(a, b)`.
+pub fn ext_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    let (mut r0, mut r1) = (a as i128, b as i128);
+    let (mut s0, mut s1) = (1i128, 0i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (s0, s1) = (s1, s0 - q * s1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+
+    if r0 < 0 {
+        (r0, s0, t0) = (-r0, -s0, -t0);
+    }
+
+    Some((
+        r0.try_into().ok()?,
+        s0.try_into().ok()?,
+        t0.try_into().ok()?,
+    ))
+}
+
+/// Returns `x` in `0..m` with `a * x ≡ 1 (mod m)`, if one exists.
+pub fn mod_inv(a: i64, m: i64) -> Option<i64> {
+    if m <= 0 {
+        return None;
+    }
+    let (g, x, _) = ext_gcd(a, m)?;
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m))
+}
+
+/// Combines congruences `x ≡ r (mod m)` into a single `(r, m)` with `r` in `0..m`.
+///
+/// Moduli need not be coprime. Returns `None` if the system is inconsistent, a
+/// modulus isn't positive, or the combined modulus overflows `i64`.
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    let mut result: (i64, i64) = (0, 1);
+
+    for &(r, m) in congruences {
+        if m <= 0 {
+            return None;
+        }
+        let (r0, m0) = result;
+        let (g, p, _) = ext_gcd(m0, m)?;
+        let diff = r as i128 - r0 as i128;
+        if diff % g as i128 != 0 {
+            return None;
+        }
+
+        let step = (m / g) as i128;
+        let k = ((diff / g as i128) * p as i128).rem_euclid(step);
+        let modulus = m0 as i128 * step;
+        let value = (r0 as i128 + m0 as i128 * k).rem_euclid(modulus);
+        result = (value.try_into().ok()?, modulus.try_into().ok()?);
+    }
+
+    Some(result)
+}
+
+/// An exact fraction in lowest terms with a positive denominator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ratio {
+    pub num: i64,
+    pub den: i64,
+}
+
+impl Ratio {
+    pub fn new(num: i64, den: i64) -> Option<Ratio> {
+        Self::reduce(num as i128, den as i128)
+    }
+
+    fn reduce(num: i128, den: i128) -> Option<Ratio> {
+        let g = match den.cmp(&0) {
+            Ordering::Equal => return None,
+            Ordering::Less => -(gcd_i128(num, den)),
+            Ordering::Greater => gcd_i128(num, den),
+        };
+        Some(Ratio {
+            num: (num / g).try_into().ok()?,
+            den: (den / g).try_into().ok()?,
+        })
+    }
+
+    /// Returns the value if it is a whole number.
+    pub fn to_integer(self) -> Option<i64> {
+        (self.den == 1).then_some(self.num)
+    }
+}
+
+fn gcd_i128(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Solves `[[a, b], [c, d]] * [x, y] = [e, f]` exactly via Cramer's rule.
+///
+/// Returns `None` if the matrix is singular or a result doesn't fit in `i64`.
+pub fn solve_2x2(m: [[i64; 2]; 2], rhs: [i64; 2]) -> Option<[Ratio; 2]> {
+    let [[a, b], [c, d]] = m.map(|row| row.map(|v| v as i128));
+    let [e, f] = rhs.map(|v| v as i128);
+
+    let det = a * d - b * c;
+    if det == 0 {
+        return None;
+    }
+
+    Some([
+        Ratio::reduce(d * e - b * f, det)?,
+        Ratio::reduce(a * f - c * e, det)?,
+    ])
+}
+
+/// Number of decimal digits in `n`; `digit_count(0)` is 1.
+pub fn digit_count(n: u64) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Splits a number with an even digit count into its high and low halves.
+///
+/// `split_digits(253000)` is `Some((253, 0))`; odd digit counts give `None`.
+pub fn split_digits(n: u64) -> Option<(u64, u64)> {
+    let digits = digit_count(n);
+    if !digits.is_multiple_of(2) {
+        return None;
+    }
+    let div = 10u64.pow(digits / 2);
+    Some((n / div, n % div))
+}
+
+/// Appends the digits of `right` to `left`: `concat(12, 345)` is `Some(12345)`.
+///
+/// Returns `None` on overflow.
+pub fn concat(left: u64, right: u64) -> Option<u64> {
+    left.checked_mul(10u64.checked_pow(digit_count(right))?)?
+        .checked_add(right)
+}
+
+/// The integer `k`th root of `n`, rounded down. `k` must be positive.
+pub fn iroot(n: u64, k: u32) -> u64 {
+    assert!(k > 0, "zeroth root");
+    if k == 1 || n < 2 {
+        return n;
+    }
+    // Start from the floating point estimate and correct it in whole steps:
+    let mut r = (n as f64).powf(1.0 / k as f64).round() as u64;
+    while r.checked_pow(k).is_none_or(|p| p > n) {
+        r -= 1;
+    }
+    while (r + 1).checked_pow(k).is_some_and(|p| p <= n) {
+        r += 1;
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(101, 103), Some(10403));
+        assert_eq!(lcm(u64::MAX, 2), None);
+    }
+
+    #[test]
+    fn test_ext_gcd() {
+        let (g, x, y) = ext_gcd(240, 46).unwrap();
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+        assert_eq!(mod_inv(3, 11), Some(4));
+        assert_eq!(mod_inv(2, 4), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_solve_2x2() {
+        let [x, y] = solve_2x2([[94, 22], [34, 67]], [8400, 5400]).unwrap();
+        assert_eq!((x.to_integer(), y.to_integer()), (Some(80), Some(40)));
+
+        let [x, _] = solve_2x2([[1, 1], [1, -1]], [1, 0]).unwrap();
+        assert_eq!(x, Ratio { num: 1, den: 2 });
+        assert_eq!(x.to_integer(), None);
+
+        assert_eq!(solve_2x2([[1, 2], [2, 4]], [3, 6]), None);
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digit_count(0), 1);
+        assert_eq!(digit_count(9), 1);
+        assert_eq!(digit_count(10), 2);
+        assert_eq!(digit_count(u64::MAX), 20);
+        assert_eq!(split_digits(253000), Some((253, 0)));
+        assert_eq!(split_digits(1000), Some((10, 0)));
+        assert_eq!(split_digits(123), None);
+        assert_eq!(concat(12, 345), Some(12345));
+        assert_eq!(concat(12, 0), Some(120));
+        assert_eq!(concat(u64::MAX / 10, 99), None);
+    }
+
+    #[test]
+    fn test_iroot() {
+        assert_eq!(iroot(0, 3), 0);
+        assert_eq!(iroot(26, 3), 2);
+        assert_eq!(iroot(27, 3), 3);
+        assert_eq!(iroot(u64::MAX, 2), u32::MAX as u64);
+        assert_eq!(iroot(u64::MAX, 64), 1);
+        assert_eq!(iroot(1 << 40, 40), 2);
+        assert_eq!(iroot(10, 1), 10);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{LocationLists, ParseError};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Most runs merged at once; more runs are first merged down in groups of this many.
+const FAN_IN: usize = 64;
+
+/// Reads `path` whole for the large-input mode, sorting both lists with `radix_sort`.
+pub fn read_large(path: &str) -> Result<LocationLists, ParseError> {
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for row in ByteScanner::new(&fs::read(path)?) {
+        let (a, b) = row?;
+        left.push(a);
+        right.push(b);
+    }
+
+    radix_sort(&mut left);
+    radix_sort(&mut right);
+    Ok(LocationLists::from_sorted(left, right))
+}
+
+/// Returns the total distance and the similarity of `path` without holding the lists.
+///
+/// The file is read `chunk_size` bytes at a time. Every `chunk_size / 16` rows both columns
+/// are sorted and spilled to temporary files as sorted runs, which are then merged back into
+/// one sorted stream per column. Memory stays at a few times `chunk_size` however long the
+/// input is.
+pub fn chunked_totals(path: &str, chunk_size: usize) -> Result<(i64, i64), ParseError> {
+    let rows_per_run = (chunk_size / 16).max(1);
+    let mut runs = Runs::new()?;
+    let mut left = Vec::with_capacity(rows_per_run);
+    let mut right = Vec::with_capacity(rows_per_run);
+
+    scan_chunked(File::open(path)?, chunk_size, |a, b| {
+        left.push(a);
+        right.push(b);
+        if left.len() == rows_per_run {
+            runs.spill(&mut left, &mut right)?;
+        }
+        Ok(())
+    })?;
+    if !left.is_empty() {
+        runs.spill(&mut left, &mut right)?;
+    }
+    runs.merge_down(chunk_size)?;
+
+    let (mut left, mut right) = runs.open(chunk_size)?;
+    let mut distance = 0;
+    while let (Some(a), Some(b)) = (left.next()?, right.next()?) {
+        distance += (a - b).abs();
+    }
+
+    let (mut left, mut right) = runs.open(chunk_size)?;
+    let mut similarity = 0;
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if b < a {
+            right.next()?;
+        } else if b > a {
+            left.next()?;
+        } else {
+            let left_run = left.skip_equal(a)?;
+            let right_run = right.skip_equal(a)?;
+            similarity += a * (left_run * right_run) as i64;
+        }
+    }
+    Ok((distance, similarity))
+}
+
+/// Sorted runs of the two columns, kept in a temporary directory removed on drop.
+struct Runs {
+    dir: PathBuf,
+    next: usize,
+    left: Vec<PathBuf>,
+    right: Vec<PathBuf>,
+}
+
+impl Runs {
+    fn new() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("d1-runs-{}-{}", process::id(), n));
+        fs::create_dir_all(&dir)?;
+        Ok(Runs {
+            dir,
+            next: 0,
+            left: Vec::new(),
+            right: Vec::new(),
+        })
+    }
+
+    /// Sorts both buffers and writes each out as a new run, leaving them empty.
+    fn spill(&mut self, left: &mut Vec<i64>, right: &mut Vec<i64>) -> io::Result<()> {
+        for (values, column) in [(left, 0), (right, 1)] {
+            radix_sort(values);
+            let (path, mut out) = self.create()?;
+            for v in values.drain(..) {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            out.flush()?;
+            match column {
+                0 => self.left.push(path),
+                _ => self.right.push(path),
+            }
+        }
+        Ok(())
+    }
+
+    fn create(&mut self) -> io::Result<(PathBuf, BufWriter<File>)> {
+        let path = self.dir.join(self.next.to_string());
+        self.next += 1;
+        let out = BufWriter::new(File::create(&path)?);
+        Ok((path, out))
+    }
+
+    /// Merges groups of runs until each column has at most `FAN_IN` left.
+    fn merge_down(&mut self, chunk_size: usize) -> io::Result<()> {
+        let left = std::mem::take(&mut self.left);
+        self.left = self.merge_column(left, chunk_size)?;
+        let right = std::mem::take(&mut self.right);
+        self.right = self.merge_column(right, chunk_size)?;
+        Ok(())
+    }
+
+    fn merge_column(
+        &mut self,
+        mut runs: Vec<PathBuf>,
+        chunk_size: usize,
+    ) -> io::Result<Vec<PathBuf>> {
+        while runs.len() > FAN_IN {
+            let mut merged = Vec::new();
+            for group in runs.chunks(FAN_IN) {
+                let mut merge = Merge::open(group, chunk_size)?;
+                let (path, mut out) = self.create()?;
+                while let Some(v) = merge.next()? {
+                    out.write_all(&v.to_le_bytes())?;
+                }
+                out.flush()?;
+                for run in group {
+                    fs::remove_file(run)?;
+                }
+                merged.push(path);
+            }
+            runs = merged;
+        }
+        Ok(runs)
+    }
+
+    fn open(&self, chunk_size: usize) -> io::Result<(Merge, Merge)> {
+        Ok((
+            Merge::open(&self.left, chunk_size)?,
+            Merge::open(&self.right, chunk_size)?,
+        ))
+    }
+}
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A sorted stream merged from sorted runs, reading each run through its share of
+/// `chunk_size` bytes of buffer.
+struct Merge {
+    runs: Vec<BufReader<File>>,
+    heads: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl Merge {
+    fn open(paths: &[PathBuf], chunk_size: usize) -> io::Result<Self> {
+        let capacity = (chunk_size / paths.len().max(1)).max(8);
+        let mut merge = Merge {
+            runs: Vec::with_capacity(paths.len()),
+            heads: BinaryHeap::with_capacity(paths.len()),
+        };
+        for path in paths {
+            merge
+                .runs
+                .push(BufReader::with_capacity(capacity, File::open(path)?));
+            merge.advance(merge.runs.len() - 1)?;
+        }
+        Ok(merge)
+    }
+
+    fn advance(&mut self, run: usize) -> io::Result<()> {
+        let mut bytes = [0; 8];
+        match self.runs[run].read_exact(&mut bytes) {
+            Ok(()) => self.heads.push(Reverse((i64::from_le_bytes(bytes), run))),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {}
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<i64> {
+        self.heads.peek().map(|Reverse((v, _))| *v)
+    }
+
+    fn next(&mut self) -> io::Result<Option<i64>> {
+        let Some(Reverse((v, run))) = self.heads.pop() else {
+            return Ok(None);
+        };
+        self.advance(run)?;
+        Ok(Some(v))
+    }
+
+    /// Consumes every value equal to `v` at the front, returning how many there were.
+    fn skip_equal(&mut self, v: i64) -> io::Result<usize> {
+        let mut count = 0;
+        while self.peek() == Some(v) {
+            self.next()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Scans a buffer of `<left> <right>` lines, reading the numbers straight from the bytes.
+pub struct ByteScanner<'a> {
+    rest: &'a [u8],
+    line: usize,
+}
+
+impl<'a> ByteScanner<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        ByteScanner { rest: buf, line: 0 }
+    }
+}
+
+impl Iterator for ByteScanner<'_> {
+    type Item = Result<(i64, i64), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() {
+            let end = self
+                .rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .unwrap_or(self.rest.len());
+            let line = &self.rest[..end];
+            self.rest = self.rest.get(end + 1..).unwrap_or(&[]);
+            self.line += 1;
+
+            match scan_line(line) {
+                Ok(Some(row)) => return Some(Ok(row)),
+                Ok(None) => continue,
+                Err(reason) => {
+                    return Some(Err(ParseError::Line {
+                        line: self.line,
+                        text: String::from_utf8_lossy(line).into_owned(),
+                        reason,
+                    }));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Returns `None` for a blank line.
+fn scan_line(line: &[u8]) -> Result<Option<(i64, i64)>, String> {
+    let mut columns = line
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|c| !c.is_empty());
+
+    let row = match (columns.next(), columns.next()) {
+        (None, _) => return Ok(None),
+        (Some(a), Some(b)) => (scan_int(a)?, scan_int(b)?),
+        (Some(_), None) => return Err("expected 2 columns, found 1".to_owned()),
+    };
+
+    match columns.count() {
+        0 => Ok(Some(row)),
+        n => Err(format!("expected 2 columns, found {}", n + 2)),
+    }
+}
+
+fn scan_int(bytes: &[u8]) -> Result<i64, String> {
+    let invalid = || format!("invalid ID {:?}", String::from_utf8_lossy(bytes));
+    let (negative, digits) = match bytes {
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        _ => (false, bytes),
+    };
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut value: i64 = 0;
+    for &b in digits {
+        if !b.is_ascii_digit() {
+            return Err(invalid());
+        }
+        let digit = (b - b'0') as i64;
+        value = value
+            .checked_mul(10)
+            .and_then(|v| {
+                if negative {
+                    v.checked_sub(digit)
+                } else {
+                    v.checked_add(digit)
+                }
+            })
+            .ok_or_else(invalid)?;
+    }
+    Ok(value)
+}
+
+/// Reads `reader` through a buffer of about `chunk_size` bytes, passing each row to `row`.
+fn scan_chunked<R: Read>(
+    mut reader: R,
+    chunk_size: usize,
+    mut row: impl FnMut(i64, i64) -> io::Result<()>,
+) -> Result<(), ParseError> {
+    let mut buf = vec![0u8; chunk_size.max(1)];
+    let mut filled = 0;
+    let mut line = 0;
+
+    loop {
+        // Only a single line longer than the chunk size makes the buffer grow:
+        if filled == buf.len() {
+            buf.resize(buf.len() * 2, 0);
+        }
+        let n = reader.read(&mut buf[filled..])?;
+        let eof = n == 0;
+        filled += n;
+
+        let end = if eof {
+            filled
+        } else {
+            match buf[..filled].iter().rposition(|&b| b == b'\n') {
+                Some(pos) => pos + 1,
+                None => continue,
+            }
+        };
+
+        let mut scanner = ByteScanner {
+            rest: &buf[..end],
+            line,
+        };
+        for next in &mut scanner {
+            let (a, b) = next?;
+            row(a, b)?;
+        }
+        line = scanner.line;
+
+        buf.copy_within(end..filled, 0);
+        filled -= end;
+        if eof {
+            return Ok(());
+        }
+    }
+}
+
+/// LSD radix sort, one byte per pass. Passes where every value shares the same byte are
+/// skipped.
+///
+/// Sorts in place, alternating between `values` and one scratch buffer of the same length.
+pub fn radix_sort(values: &mut [i64]) {
+    // Flipping the sign bit makes the unsigned order match the signed order:
+    let byte = |v: i64, shift: u32| ((v as u64 ^ (1 << 63)) >> shift) as usize & 0xff;
+    let mut scratch = vec![0i64; values.len()];
+    let mut in_scratch = false;
+
+    for shift in (0..64).step_by(8) {
+        let (src, dst) = match in_scratch {
+            true => (&scratch[..], &mut values[..]),
+            false => (&values[..], &mut scratch[..]),
+        };
+        let mut counts = [0usize; 256];
+        for &v in src {
+            counts[byte(v, shift)] += 1;
+        }
+        if counts.contains(&src.len()) {
+            continue;
+        }
+
+        let mut offset = 0;
+        for count in counts.iter_mut() {
+            (*count, offset) = (offset, offset + *count);
+        }
+        for &v in src {
+            let bucket = &mut counts[byte(v, shift)];
+            dst[*bucket] = v;
+            *bucket += 1;
+        }
+        in_scratch = !in_scratch;
+    }
+
+    if in_scratch {
+        values.copy_from_slice(&scratch);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::iter::zip;
+
+mod large;
+
+pub use large::{ByteScanner, chunked_totals, radix_sort, read_large};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    Line {
+        line: usize,
+        text: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{}", e),
+            ParseError::Line { line, text, reason } => {
+                write!(f, "line {}: {} ({:?})", line, reason, text)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+/// An ID present in both lists, with how often it occurs in each.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SharedId {
+    pub id: i64,
+    pub left: usize,
+    pub right: usize,
+}
+
+/// The two location ID lists, each kept sorted.
+#[derive(Debug)]
+pub struct LocationLists {
+    left: Vec<i64>,
+    right: Vec<i64>,
+}
+
+impl LocationLists {
+    /// Returns `None` if the lists differ in length.
+    pub fn new(mut left: Vec<i64>, mut right: Vec<i64>) -> Option<Self> {
+        if left.len() != right.len() {
+            return None;
+        }
+        left.sort_unstable();
+        right.sort_unstable();
+        Some(LocationLists { left, right })
+    }
+
+    fn from_sorted(left: Vec<i64>, right: Vec<i64>) -> Self {
+        debug_assert!(left.is_sorted() && right.is_sorted() && left.len() == right.len());
+        LocationLists { left, right }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ParseError> {
+        Self::parse(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads one `<left> <right>` pair per line; blank lines are skipped.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self, ParseError> {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let bad_line = |reason: String| ParseError::Line {
+                line: idx + 1,
+                text: line.clone(),
+                reason,
+            };
+
+            let columns: Vec<&str> = line.split_ascii_whitespace().collect();
+            match columns[..] {
+                [] => continue,
+                [a, b] => {
+                    for (value, list) in [(a, &mut left), (b, &mut right)] {
+                        let value = value
+                            .parse::<i64>()
+                            .map_err(|e| bad_line(format!("invalid ID {:?}: {}", value, e)))?;
+                        list.push(value);
+                    }
+                }
+                _ => {
+                    return Err(bad_line(format!(
+                        "expected 2 columns, found {}",
+                        columns.len()
+                    )));
+                }
+            }
+        }
+
+        Ok(Self::new(left, right).expect("every row adds to both lists"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    fn deltas(&self) -> impl Iterator<Item = i64> + '_ {
+        zip(&self.left, &self.right).map(|(a, b)| (a - b).abs())
+    }
+
+    /// Part 1: sum of the distances between the paired, sorted lists.
+    pub fn total_distance(&self) -> i64 {
+        self.deltas().sum()
+    }
+
+    /// Part 2: each left ID multiplied by how often it appears in the right list.
+    pub fn similarity(&self) -> i64 {
+        let counts = collapse_vec(&self.right);
+        self.left
+            .iter()
+            .map(|v| v * *counts.get(v).unwrap_or(&0) as i64)
+            .sum()
+    }
+
+    /// Same as `similarity`, but walks the two sorted lists in step instead of building a
+    /// count table.
+    pub fn similarity_merge(&self) -> i64 {
+        let (left, right) = (&self.left[..], &self.right[..]);
+        let (mut i, mut j) = (0, 0);
+        let mut sum = 0;
+
+        while i < left.len() && j < right.len() {
+            let id = left[i];
+            if right[j] < id {
+                j += 1;
+            } else if right[j] > id {
+                i += 1;
+            } else {
+                let left_run = left[i..].partition_point(|&v| v == id);
+                let right_run = right[j..].partition_point(|&v| v == id);
+                sum += id * (left_run * right_run) as i64;
+                i += left_run;
+                j += right_run;
+            }
+        }
+        sum
+    }
+
+    /// Counts of pair distances, grouped into buckets of `width` keyed by each bucket's lower
+    /// bound.
+    pub fn distance_histogram(&self, width: i64) -> BTreeMap<i64, usize> {
+        let width = width.max(1);
+        let mut histogram = BTreeMap::new();
+        for delta in self.deltas() {
+            *histogram.entry(delta - delta % width).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Number of entries in the (left, right) list whose ID never appears in the other list.
+    pub fn unmatched(&self) -> (usize, usize) {
+        let left = collapse_vec(&self.left);
+        let right = collapse_vec(&self.right);
+        let missing = |ids: &[i64], other: &HashMap<i64, usize>| {
+            ids.iter().filter(|id| !other.contains_key(id)).count()
+        };
+        (missing(&self.left, &right), missing(&self.right, &left))
+    }
+
+    /// The `n` IDs found in both lists with the most combined occurrences.
+    pub fn most_frequent_shared(&self, n: usize) -> Vec<SharedId> {
+        let right = collapse_vec(&self.right);
+        let mut shared: Vec<SharedId> = collapse_vec(&self.left)
+            .into_iter()
+            .filter_map(|(id, left)| right.get(&id).map(|&right| SharedId { id, left, right }))
+            .collect();
+        shared.sort_by_key(|s| (std::cmp::Reverse(s.left + s.right), s.id));
+        shared.truncate(n);
+        shared
+    }
+
+    /// Median pair distance, or `None` for empty lists.
+    pub fn median_delta(&self) -> Option<f64> {
+        let mut deltas: Vec<i64> = self.deltas().collect();
+        deltas.sort_unstable();
+        let mid = deltas.len() / 2;
+        match deltas.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(deltas[mid] as f64),
+            _ => Some((deltas[mid - 1] + deltas[mid]) as f64 / 2.0),
+        }
+    }
+}
+
+fn collapse_vec(v: &[i64]) -> HashMap<i64, usize> {
+    let mut result = HashMap::<i64, usize>::new();
+    for x in v {
+        *result.entry(*x).or_default() += 1;
+    }
+
+    result
+}
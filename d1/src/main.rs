@@ -1,66 +1,83 @@
-use std::collections::HashMap;
-use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::iter::zip;
+use clap::Parser;
+use d1::{LocationLists, chunked_totals, read_large};
+use std::error::Error;
 
-#[cfg(test)]
-mod tests;
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
 
-fn main() {
-    if let Some(arg) = env::args().nth(1) {
-        let (mut v1, mut v2) = process_input(&arg);
+    /// Print statistics about the lists
+    #[arg(short, long)]
+    stats: bool,
 
-        v1.sort();
-        v2.sort();
+    /// Bucket width for the distance histogram
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(i64).range(1..))]
+    bucket: i64,
 
-        let delta = calculate_delta(&v1, &v2);
-        println!("Delta: {}", delta);
+    /// Use the large-input mode: byte scanner, radix sort and merge-walk similarity
+    #[arg(short, long)]
+    large: bool,
 
-        let sim = calculate_sim(&v1, &v2);
-        println!("Simularity: {}", sim);
-    } else {
-        usage();
-    }
+    /// With --large, sort the lists as runs of about this many bytes spilled to temporary
+    /// files, so memory doesn't grow with the input
+    #[arg(
+        long,
+        value_name = "BYTES",
+        requires = "large",
+        conflicts_with = "stats"
+    )]
+    chunk: Option<usize>,
 }
 
-fn process_input(path: &str) -> (Vec<i32>, Vec<i32>) {
-    let file = File::open(path).unwrap();
-    let reader = BufReader::new(file);
-    let mut v1 = Vec::<i32>::new();
-    let mut v2 = Vec::<i32>::new();
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if let Some(chunk) = cli.chunk {
+        let (distance, sim) = chunked_totals(&cli.file, chunk)?;
+        println!("Delta: {}", distance);
+        println!("Simularity: {}", sim);
+        return Ok(());
+    }
 
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let mut iter = line.split_ascii_whitespace();
+    let (lists, sim) = if cli.large {
+        let lists = read_large(&cli.file)?;
+        let sim = lists.similarity_merge();
+        (lists, sim)
+    } else {
+        let lists = LocationLists::from_file(&cli.file)?;
+        let sim = lists.similarity();
+        (lists, sim)
+    };
 
-        let a = iter.next().unwrap().parse::<i32>().unwrap();
-        let b = iter.next().unwrap().parse::<i32>().unwrap();
+    println!("Delta: {}", lists.total_distance());
+    println!("Simularity: {}", sim);
 
-        v1.push(a);
-        v2.push(b);
+    if cli.stats {
+        print_stats(&lists, cli.bucket);
     }
-    (v1, v2)
+    Ok(())
 }
 
-fn calculate_delta(v1: &Vec<i32>, v2: &Vec<i32>) -> i32 {
-    zip(v1, v2).map(|(a, b)| (a - b).abs()).sum()
-}
-
-fn collapse_vec(v: &Vec<i32>) -> HashMap<i32, i32> {
-    let mut result = HashMap::<i32, i32>::new();
-    for x in v {
-        *result.entry(*x).or_default() += 1;
+fn print_stats(lists: &LocationLists, bucket: i64) {
+    println!();
+    println!("Pairs: {}", lists.len());
+    if let Some(median) = lists.median_delta() {
+        println!("Median delta: {}", median);
     }
 
-    result
-}
+    let (left, right) = lists.unmatched();
+    println!("Unmatched IDs: {} left, {} right", left, right);
 
-fn calculate_sim(v1: &Vec<i32>, v2: &Vec<i32>) -> i32 {
-    let m2 = collapse_vec(v2);
-    v1.into_iter().map(|v| v * m2.get(v).unwrap_or(&0)).sum()
-}
+    println!("Most frequent shared IDs:");
+    for shared in lists.most_frequent_shared(5) {
+        println!(
+            "  {}: {} left, {} right",
+            shared.id, shared.left, shared.right
+        );
+    }
 
-fn usage() {
-    println!("Usage: <input>");
+    println!("Distance histogram:");
+    for (start, count) in lists.distance_histogram(bucket) {
+        println!("  {:>8}..{:<8} {}", start, start + bucket, count);
+    }
 }
@@ -1,5 +1,7 @@
 use super::*;
 
+const SAMPLE: &str = "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+
 #[test]
 fn test_collapse_vec() {
     let v = vec![1, 2, 3, 3, 3, 3, 4, 9, 10, 10];
@@ -7,3 +9,93 @@ fn test_collapse_vec() {
     let result = collapse_vec(&v);
     assert_eq!(expected, result);
 }
+
+#[test]
+fn test_sample() {
+    let lists = LocationLists::parse(SAMPLE.as_bytes()).unwrap();
+    assert_eq!(lists.total_distance(), 11);
+    assert_eq!(lists.similarity(), 31);
+    assert_eq!(lists.median_delta(), Some(1.5));
+    assert_eq!(lists.unmatched(), (2, 2));
+    assert_eq!(
+        lists.most_frequent_shared(1),
+        vec![SharedId {
+            id: 3,
+            left: 3,
+            right: 3
+        }]
+    );
+    assert_eq!(
+        lists.distance_histogram(2),
+        BTreeMap::from([(0, 3), (2, 2), (4, 1)])
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    for (input, line) in [("1 2\n3\n", 2), ("1 2 3\n", 1), ("1 2\n\n1 x\n", 3)] {
+        match LocationLists::parse(input.as_bytes()) {
+            Err(ParseError::Line { line: l, .. }) => assert_eq!(l, line),
+            r => panic!("{:?}: unexpected {:?}", input, r),
+        }
+    }
+}
+
+fn generated(rows: usize) -> String {
+    let mut seed: u64 = 12345;
+    let mut next = || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as i64 % 2000 - 500
+    };
+    (0..rows)
+        .map(|_| format!("{}   {}\n", next(), next()))
+        .collect()
+}
+
+#[test]
+fn test_radix_sort() {
+    let mut v = vec![5, -3, i64::MAX, 0, i64::MIN, 70000, -70000, 5];
+    let mut expected = v.clone();
+    expected.sort();
+    radix_sort(&mut v);
+    assert_eq!(v, expected);
+
+    // A single pass leaves the values in the scratch buffer, to be copied back:
+    let mut v = vec![3, 200, 1, 255, 0];
+    radix_sort(&mut v);
+    assert_eq!(v, vec![0, 1, 3, 200, 255]);
+}
+
+#[test]
+fn test_modes_agree() {
+    let input = generated(5000);
+    let path = std::env::temp_dir().join("d1-modes-agree.txt");
+    std::fs::write(&path, &input).unwrap();
+    let path = path.to_str().unwrap();
+
+    let lists = LocationLists::parse(input.as_bytes()).unwrap();
+    assert_eq!(lists.similarity(), lists.similarity_merge());
+
+    let large = read_large(path).unwrap();
+    assert_eq!(large.left, lists.left);
+    assert_eq!(large.right, lists.right);
+    assert_eq!(large.similarity_merge(), lists.similarity());
+
+    // Down to one row a run, which needs merging down before the final merge:
+    let totals = (lists.total_distance(), lists.similarity());
+    for chunk in [1, 7, 4096, 1 << 20] {
+        assert_eq!(chunked_totals(path, chunk).unwrap(), totals);
+    }
+}
+
+#[test]
+fn test_byte_scanner_errors() {
+    for (input, line) in [("1 2\n3\n", 2), ("1 2 3\n", 1), ("1 2\n\n1 x\n", 3)] {
+        match ByteScanner::new(input.as_bytes()).find(|r| r.is_err()) {
+            Some(Err(ParseError::Line { line: l, .. })) => assert_eq!(l, line),
+            r => panic!("{:?}: unexpected {:?}", input, r),
+        }
+    }
+}
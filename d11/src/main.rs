@@ -1,10 +1,11 @@
+use aoc::num;
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::process;
 
-fn main() -> Result<(), Box<(dyn std::error::Error + 'static)>> {
+fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
     let args: Vec<String> = env::args().collect();
     if args.len() != 3 {
         eprintln!("Usage: {} <input_file> <blink count>", args[0]);
@@ -51,15 +52,11 @@ fn stone_next(value: u64) -> Vec<u64> {
     let mut result = Vec::new();
     if value == 0 {
         result.push(1);
+    } else if let Some((high, low)) = num::split_digits(value) {
+        result.push(high);
+        result.push(low);
     } else {
-        let s = value.to_string();
-        if s.len() % 2 == 0 {
-            let pos = s.len() / 2;
-            result.push(s[0..pos].parse::<u64>().unwrap());
-            result.push(s[pos..].parse::<u64>().unwrap());
-        } else {
-            result.push(value * 2024);
-        }
+        result.push(value * 2024);
     }
 
     result
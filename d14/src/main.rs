@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use aoc::num;
 use clap::Parser;
 use regex::Regex;
 use std::collections::HashMap;
@@ -29,9 +30,13 @@ struct Cli {
     /// Easter egg hunt
     #[arg(long, value_name = "SECONDS")]
     hunt: Option<i32>,
+
+    /// Locate the easter egg by combining the x and y periods (CRT)
+    #[arg(long)]
+    tree: bool,
 }
 
-fn main() -> Result<(), Box<(dyn Error + 'static)>> {
+fn main() -> Result<(), Box<dyn Error + 'static>> {
     let cli = Cli::parse();
 
     let robots = parse_robot_file(&cli.input)?;
@@ -55,7 +60,20 @@ fn main() -> Result<(), Box<(dyn Error + 'static)>> {
         return Ok(());
     }
 
+    if cli.tree {
+        let sec = lobby.find_tree(&robots).ok_or("No tree found")?;
+        let mut frame = Frame::new();
+        for robot in robots.iter() {
+            frame.add(lobby.wrap(&robot.steps(sec)));
+        }
+        lobby.print_frame(&frame, sec);
+        println!("Tree: {}", sec);
+        return Ok(());
+    }
+
     if let Some(sec) = cli.hunt {
+        // Every robot is back where it started after `period` seconds:
+        let sec = sec.min(lobby.period().ok_or("Lobby too large")?);
         let mut min_distance: u32 = u32::MAX;
         for i in 1..=sec {
             let mut frame = Frame::new();
@@ -157,6 +175,15 @@ impl Frame {
     }
 }
 
+/// Variance scaled by `n²`, which keeps the arithmetic in integers.
+fn spread(values: impl Iterator<Item = i32>) -> i64 {
+    let (n, sum, sum_sq) = values.fold((0i64, 0i64, 0i64), |(n, sum, sum_sq), v| {
+        let v = v as i64;
+        (n + 1, sum + v, sum_sq + v * v)
+    });
+    n * sum_sq - sum * sum
+}
+
 #[derive(Debug)]
 enum Quad {
     NE,
@@ -186,6 +213,28 @@ impl Lobby {
         Vec2 { x: new_x, y: new_y }
     }
 
+    fn period(&self) -> Option<i32> {
+        let period = num::lcm(self.width as u64, self.height as u64)?;
+        period.try_into().ok()
+    }
+
+    /// x positions repeat every `width` seconds and y positions every `height`. The tree
+    /// is where both axes are most tightly clustered, so find the tightest second for each
+    /// axis independently and combine them with the CRT.
+    fn find_tree(&self, robots: &[Robot]) -> Option<i32> {
+        let tightest = |period: i32, axis: fn(Vec2) -> i32| {
+            (0..period)
+                .min_by_key(|&sec| spread(robots.iter().map(|r| axis(self.wrap(&r.steps(sec))))))
+        };
+        let x_sec = tightest(self.width, |v| v.x)?;
+        let y_sec = tightest(self.height, |v| v.y)?;
+        let (sec, _) = num::crt(&[
+            (x_sec as i64, self.width as i64),
+            (y_sec as i64, self.height as i64),
+        ])?;
+        sec.try_into().ok()
+    }
+
     fn print_frame(&self, frame: &Frame, steps: i32) {
         let cap = 80 + self.height * (self.width + 1);
         let mut s = String::with_capacity(cap as usize);
@@ -277,7 +326,7 @@ impl Robot {
         self.start + self.dir * secs
     }
 }
-fn parse_robot_file(path: &String) -> Result<Vec<Robot>, Box<dyn Error>> {
+fn parse_robot_file(path: &str) -> Result<Vec<Robot>, Box<dyn Error>> {
     let robot_re = Regex::new(r"p=(-*\d+),(-*\d+) v=(-*\d+),(-*\d+)")?;
 
     let mut robots = Vec::new();
@@ -1,43 +1,45 @@
-use std::env;
+use clap::Parser;
+use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Lines};
 
 #[cfg(test)]
 mod tests;
 
-fn main() {
-    if let Some(arg) = env::args().nth(1) {
-        run(&arg);
-    } else {
-        usage();
-    }
-}
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
 
-struct SubVec {
-    v: Vec<i32>,
-    skip: usize,
-}
+    /// Number of levels the Problem Dampener may remove from a report
+    #[arg(short, long = "budget", default_value_t = 1)]
+    k: usize,
 
-impl SubVec {
-    fn new(v: Vec<i32>) -> Self {
-        SubVec { v, skip: 0 }
-    }
-}
+    /// Smallest allowed difference between adjacent levels
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+    min_step: i32,
 
-impl Iterator for SubVec {
-    type Item = Vec<i32>;
+    /// Largest allowed difference between adjacent levels
+    #[arg(long, default_value_t = 3)]
+    max_step: i32,
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.skip == self.v.len() {
-            None
-        } else {
-            let mut sv = Vec::with_capacity(self.v.len() - 1);
-            sv.extend_from_slice(&self.v[0..self.skip]);
-            sv.extend_from_slice(&self.v[self.skip + 1..]);
-            self.skip += 1;
-            Some(sv)
-        }
+    /// Print each unsafe report with the failing levels marked
+    #[arg(short, long)]
+    explain: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if cli.max_step < cli.min_step {
+        return Err("--max-step must be at least --min-step".into());
     }
+    let rule = StepRule {
+        min: cli.min_step,
+        max: cli.max_step,
+    };
+    run(&cli.file, cli.k, rule, cli.explain);
+    Ok(())
 }
 
 struct Reports {
@@ -71,43 +73,175 @@ impl Iterator for Reports {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Rate {
     Increasing,
     Decreasing,
 }
 
-fn get_rate(l: i32, r: i32) -> Result<Rate, ()> {
-    match l - r {
-        -3..0 => Ok(Rate::Increasing),
-        0 => Err(()),
-        1..4 => Ok(Rate::Decreasing),
-        _ => Err(()),
+/// The allowed size of the difference between adjacent levels.
+#[derive(Debug, Clone, Copy)]
+struct StepRule {
+    min: i32,
+    max: i32,
+}
+
+impl Default for StepRule {
+    fn default() -> Self {
+        StepRule { min: 1, max: 3 }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Violation {
+    ZeroStep,
+    StepBelowMinimum,
+    StepAboveMaximum,
+    Reversal,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Violation::ZeroStep => "zero step",
+            Violation::StepBelowMinimum => "step below minimum",
+            Violation::StepAboveMaximum => "step above maximum",
+            Violation::Reversal => "direction reversal",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Why a report is unsafe: the rule broken between levels `index` and `index + 1`.
+#[derive(Debug, PartialEq, Eq)]
+struct Unsafe {
+    index: usize,
+    violation: Violation,
+}
+
+fn get_rate(l: i32, r: i32, rule: StepRule) -> Result<Rate, Violation> {
+    let step = r - l;
+    if step == 0 {
+        Err(Violation::ZeroStep)
+    } else if step.abs() < rule.min {
+        Err(Violation::StepBelowMinimum)
+    } else if step.abs() > rule.max {
+        Err(Violation::StepAboveMaximum)
+    } else if step > 0 {
+        Ok(Rate::Increasing)
+    } else {
+        Ok(Rate::Decreasing)
     }
 }
-fn check_vector(v: &Vec<i32>) -> Result<Rate, ()> {
-    let rate = get_rate(v[0], v[1])?;
+
+fn check_vector(v: &[i32], rule: StepRule) -> Result<Rate, Unsafe> {
+    let pair = |index: usize| {
+        get_rate(v[index], v[index + 1], rule).map_err(|violation| Unsafe { index, violation })
+    };
+
+    let rate = pair(0)?;
     for i in 1..v.len() - 1 {
-        if rate != get_rate(v[i], v[i + 1])? {
-            return Err(());
+        if rate != pair(i)? {
+            return Err(Unsafe {
+                index: i,
+                violation: Violation::Reversal,
+            });
         }
     }
     Ok(rate)
 }
 
-fn run(path: &str) {
-    let mut safe = 0;
-    for report in Reports::new(path) {
-        for sub in SubVec::new(report) {
-            if check_vector(&sub).is_ok() {
-                safe += 1;
-                break;
+/// Finds the fewest levels to remove from `v` so the rest is safe, if at most `k` are needed.
+///
+/// For each direction, `removed[i]` is the fewest removals that keep `v[i]` as the last level
+/// of a safe prefix. A kept level can only follow one of the `k + 1` levels before it, so this
+/// is O(n * k) per report.
+fn dampen(v: &[i32], k: usize, rule: StepRule) -> Option<Vec<usize>> {
+    if v.len() <= 1 || check_vector(v, rule).is_ok() {
+        return Some(Vec::new());
+    }
+
+    [Rate::Increasing, Rate::Decreasing]
+        .into_iter()
+        .filter_map(|rate| dampen_rate(v, k, rule, rate))
+        .min_by_key(|removed| removed.len())
+}
+
+fn dampen_rate(v: &[i32], k: usize, rule: StepRule, rate: Rate) -> Option<Vec<usize>> {
+    let n = v.len();
+    let mut removed = vec![usize::MAX; n];
+    let mut prev: Vec<Option<usize>> = vec![None; n];
+
+    for i in 0..n {
+        // Start the sequence here by dropping everything before:
+        removed[i] = i;
+        for j in i.saturating_sub(k + 1)..i {
+            let gap = i - 1 - j;
+            if removed[j] <= k
+                && removed[j] + gap < removed[i]
+                && get_rate(v[j], v[i], rule) == Ok(rate)
+            {
+                removed[i] = removed[j] + gap;
+                prev[i] = Some(j);
             }
         }
     }
-    println!("Safe: {}", safe);
+
+    let last = (0..n).min_by_key(|&i| removed[i] + (n - 1 - i))?;
+    if removed[last] + (n - 1 - last) > k {
+        return None;
+    }
+
+    let mut keep = vec![false; n];
+    let mut cur = Some(last);
+    while let Some(i) = cur {
+        keep[i] = true;
+        cur = prev[i];
+    }
+    Some((0..n).filter(|&i| !keep[i]).collect())
 }
 
-fn usage() {
-    println!("Usage: <input>");
+fn explain(report: &[i32], reason: &Unsafe, fix: Option<Vec<usize>>) {
+    let levels: Vec<String> = report.iter().map(|v| v.to_string()).collect();
+    let (l, r) = (reason.index, reason.index + 1);
+    let start: usize = levels[..l].iter().map(|s| s.len() + 1).sum();
+    let width: usize = levels[l].len() + 1 + levels[r].len();
+
+    println!(
+        "{}: {} between levels {} and {} ({} -> {})",
+        levels.join(" "),
+        reason.violation,
+        l,
+        r,
+        report[l],
+        report[r]
+    );
+    println!("{}{}", " ".repeat(start), "^".repeat(width));
+    match fix {
+        Some(removed) => println!("  dampener removes levels {:?}", removed),
+        None => println!("  not fixable by the dampener"),
+    }
+}
+
+fn run(path: &str, k: usize, rule: StepRule, verbose: bool) {
+    let mut safe = 0;
+    let mut dampened = 0;
+    for report in Reports::new(path) {
+        let fix = dampen(&report, k, rule);
+        if fix.is_some() {
+            dampened += 1;
+        }
+
+        if report.len() <= 1 {
+            safe += 1;
+            continue;
+        }
+        match check_vector(&report, rule) {
+            Ok(_) => safe += 1,
+            Err(reason) if verbose => explain(&report, &reason, fix),
+            Err(_) => {}
+        }
+    }
+    println!("Safe: {}", safe);
+    println!("Safe with dampener (k = {}): {}", k, dampened);
 }
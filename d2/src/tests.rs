@@ -2,39 +2,71 @@ use super::*;
 
 #[test]
 fn test_is_safe() {
+    let rule = StepRule::default();
+    let err = |index, violation| Err(Unsafe { index, violation });
+
     let mut v = vec![1, 2, 3];
-    assert_eq!(Ok(Rate::Increasing), check_vector(&v));
+    assert_eq!(Ok(Rate::Increasing), check_vector(&v, rule));
 
     v = vec![3, 2, 1];
-    assert_eq!(Ok(Rate::Decreasing), check_vector(&v));
+    assert_eq!(Ok(Rate::Decreasing), check_vector(&v, rule));
 
     v = vec![1, 1, 1];
-    assert_eq!(Err(()), check_vector(&v));
+    assert_eq!(err(0, Violation::ZeroStep), check_vector(&v, rule));
 
     v = vec![1, 2, 2];
-    assert_eq!(Err(()), check_vector(&v));
+    assert_eq!(err(1, Violation::ZeroStep), check_vector(&v, rule));
 
     v = vec![1, 4, 7];
-    assert_eq!(Ok(Rate::Increasing), check_vector(&v));
+    assert_eq!(Ok(Rate::Increasing), check_vector(&v, rule));
 
     v = vec![7, 4, 1];
-    assert_eq!(Ok(Rate::Decreasing), check_vector(&v));
+    assert_eq!(Ok(Rate::Decreasing), check_vector(&v, rule));
 
     v = vec![1, 4, 8];
-    assert_eq!(Err(()), check_vector(&v));
+    assert_eq!(err(1, Violation::StepAboveMaximum), check_vector(&v, rule));
 
     v = vec![8, 5, 1];
-    assert_eq!(Err(()), check_vector(&v));
+    assert_eq!(err(1, Violation::StepAboveMaximum), check_vector(&v, rule));
 
     v = vec![1, 2, 1];
-    assert_eq!(Err(()), check_vector(&v));
+    assert_eq!(err(1, Violation::Reversal), check_vector(&v, rule));
+}
+
+#[test]
+fn test_step_rule() {
+    let rule = StepRule { min: 2, max: 5 };
+    assert_eq!(Ok(Rate::Increasing), check_vector(&[1, 6, 8], rule));
+    assert_eq!(
+        Err(Unsafe {
+            index: 1,
+            violation: Violation::StepBelowMinimum
+        }),
+        check_vector(&[1, 3, 4], rule)
+    );
+    assert_eq!(Some(vec![2]), dampen(&[1, 3, 4, 5], 1, rule));
 }
 
 #[test]
-fn test_subvec() {
-    let v = vec![1, 2, 3];
-    let e = vec![vec![2, 3], vec![1, 3], vec![1, 2]];
-    let sv: Vec<Vec<i32>> = SubVec::new(v).collect();
+fn test_dampen() {
+    let empty: Vec<usize> = Vec::new();
+    assert_eq!(Some(empty.clone()), dampen_default(&[7, 6, 4, 2, 1], 0));
+    assert_eq!(None, dampen_default(&[1, 2, 7, 8, 9], 1));
+    assert_eq!(None, dampen_default(&[9, 7, 6, 2, 1], 1));
+    assert_eq!(Some(vec![2]), dampen_default(&[1, 3, 2, 4, 5], 1));
+    assert_eq!(Some(vec![3]), dampen_default(&[8, 6, 4, 4, 1], 1));
+    assert_eq!(Some(empty.clone()), dampen_default(&[1, 3, 6, 7, 9], 1));
+
+    // Removals at either end:
+    assert_eq!(Some(vec![0]), dampen_default(&[9, 1, 2, 3], 1));
+    assert_eq!(Some(vec![3]), dampen_default(&[1, 2, 3, 9], 1));
+
+    // A budget of two can drop a pair:
+    assert_eq!(None, dampen_default(&[1, 2, 9, 9, 3, 4], 1));
+    assert_eq!(Some(vec![2, 3]), dampen_default(&[1, 2, 9, 9, 3, 4], 2));
+    assert_eq!(Some(empty), dampen_default(&[5], 0));
+}
 
-    assert_eq!(e, sv);
+fn dampen_default(v: &[i32], k: usize) -> Option<Vec<usize>> {
+    dampen(v, k, StepRule::default())
 }
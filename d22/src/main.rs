@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use aoc::cache::{Cache, CacheArgs};
 use aoc::read_ints;
 use clap::Parser;
 use std::collections::HashMap;
@@ -10,17 +11,29 @@ struct Cli {
     /// Input file
     #[arg(short, long)]
     file: String,
+
+    #[command(flatten)]
+    cache: CacheArgs,
 }
 
-fn main() -> Result<(), Box<(dyn Error + 'static)>> {
+const SOLVER_VERSION: u32 = 1;
+
+const SECRET_COUNT: usize = 2000;
+
+fn main() -> Result<(), Box<dyn Error + 'static>> {
     let cli = Cli::parse();
+    let cache = Cache::open("d22", SOLVER_VERSION, &cli.file, &cli.cache)?;
     let seeds = read_ints::<u64>(cli.file)?;
+    let params = format!("count={}", SECRET_COUNT);
 
-    let sum = pseudo_sum(&seeds, 2000);
+    let sum: u64 = cache.get_or_insert_with(1, &params, || pseudo_sum(&seeds, SECRET_COUNT));
     println!("Part 1: {}", sum);
 
-    let (delta, sum) = find_max(&seeds);
-    println!("Part 2: {} ({:?})", sum, delta);
+    let best: String = cache.get_or_insert_with(2, &params, || {
+        let (delta, sum) = find_max(&seeds);
+        format!("{} ({:?})", sum, delta)
+    });
+    println!("Part 2: {}", best);
 
     Ok(())
 }
@@ -37,13 +50,14 @@ impl Deltas {
     }
 }
 
-fn pseudo_sum(seeds: &Vec<u64>, count: usize) -> u64 {
+fn pseudo_sum(seeds: &[u64], count: usize) -> u64 {
     seeds.iter().map(|&seed| find_secret(seed, count)).sum()
 }
 
-fn find_max(seeds: &Vec<u64>) -> (Deltas, u64) {
-    let sums: HashMap<Deltas, u64> = seeds.iter()
-        .flat_map(|&seed| find_seed_max(seed, 2000))
+fn find_max(seeds: &[u64]) -> (Deltas, u64) {
+    let sums: HashMap<Deltas, u64> = seeds
+        .iter()
+        .flat_map(|&seed| find_seed_max(seed, SECRET_COUNT))
         .fold(HashMap::new(), |mut acc, (delta, value)| {
             acc.entry(delta)
                 .and_modify(|e| *e += value)
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
+use aoc::cache::{Cache, CacheArgs};
+use aoc::intern::{Interner, Packed, Symbols};
 use clap::Parser;
 use fixedbitset::FixedBitSet;
-use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -12,38 +13,49 @@ struct Cli {
     /// Input file
     #[arg(short, long)]
     file: String,
+
+    #[command(flatten)]
+    cache: CacheArgs,
 }
 
+const SOLVER_VERSION: u32 = 1;
+
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
-    let graph = build_graph(cli.file, 600)?;
+    let cache = Cache::open("d23", SOLVER_VERSION, &cli.file, &cli.cache)?;
+    let graph = build_graph(cli.file)?;
 
     // Part 1:
-    let triples = get_triples(&graph);
-    let tset = names_start_with(&graph, 't');
-    let t_count = triples.iter().filter(|e| e.member_of(&tset)).count();
+    let t_count: usize = cache.get_or_insert_with(1, "", || {
+        let triples = get_triples(&graph);
+        let tset = names_start_with(&graph, 't');
+        triples.iter().filter(|e| e.member_of(&tset)).count()
+    });
     println!("Part 1: {}", t_count);
 
     // Part 2:
-    let (clique, _) = max_clique(&graph);
-    display_clique(&graph, &clique);
+    let password: String = cache.get_or_insert_with(2, "", || {
+        let (clique, _) = max_clique(&graph);
+        clique_password(&graph, &clique)
+    });
+    println!("{}", password);
 
     Ok(())
 }
 
-fn display_clique(graph: &Graph, clique: &FixedBitSet) {
+fn clique_password(graph: &Graph, clique: &FixedBitSet) -> String {
     let mut cnames: Vec<&str> = Vec::new();
     for idx in clique.ones() {
-        cnames.push(&graph.nodes[idx].name);
+        cnames.push(graph.names.resolve(idx));
     }
     cnames.sort();
-    println!("{}", cnames.join(","));
+    cnames.join(",")
 }
 
 fn names_start_with(graph: &Graph, ch: char) -> FixedBitSet {
     let mut result = FixedBitSet::with_capacity(graph.capacity);
-    for (idx, node) in graph.nodes.iter().enumerate() {
-        if node.name.starts_with(ch) {
+    for (idx, name) in graph.names.iter() {
+        if name.starts_with(ch) {
             result.insert(idx);
         }
     }
@@ -128,16 +140,16 @@ fn max_clique_node(graph: &Graph, node_idx: usize, peers: &FixedBitSet) -> (Fixe
     (clique, max_clique_size)
 }
 
+type Name = Packed<2>;
+
 #[derive(Debug)]
 struct Node {
-    name: String,
     peers: FixedBitSet,
 }
 
 impl Node {
-    fn new(name: &str, capacity: usize) -> Self {
+    fn new(capacity: usize) -> Self {
         Node {
-            name: name.to_string(),
             peers: FixedBitSet::with_capacity(capacity),
         }
     }
@@ -147,57 +159,58 @@ impl Node {
 struct Graph {
     capacity: usize,
     nodes: Vec<Node>,
+    names: Symbols<Name>,
 }
 
 struct GraphBuilder {
-    names: HashMap<String, usize>,
-    nodes: Vec<Node>,
-    capacity: usize,
+    names: Interner<Name>,
+    edges: Vec<(usize, usize)>,
 }
 
 impl GraphBuilder {
-    fn new(capacity: usize) -> GraphBuilder {
+    fn new() -> GraphBuilder {
         GraphBuilder {
-            names: HashMap::new(),
-            nodes: Vec::new(),
-            capacity,
+            names: Interner::new(),
+            edges: Vec::new(),
         }
     }
 
     fn build(self) -> Graph {
-        Graph {
-            capacity: self.capacity,
-            nodes: self.nodes,
+        let capacity = self.names.len();
+        let mut nodes: Vec<Node> = (0..capacity).map(|_| Node::new(capacity)).collect();
+        for (left, right) in self.edges {
+            nodes[left].peers.insert(right);
+            nodes[right].peers.insert(left);
         }
-    }
 
-    fn find_index(&mut self, name: &str) -> usize {
-        if let Some(&i) = self.names.get(name) {
-            i
-        } else {
-            let i = self.nodes.len();
-            self.nodes.push(Node::new(name, self.capacity));
-            self.names.insert(name.to_string(), i);
-            i
+        Graph {
+            capacity,
+            nodes,
+            names: self.names.freeze(),
         }
     }
 
-    fn add_peer(&mut self, left: &str, right: &str) {
-        let left = self.find_index(left);
-        let right = self.find_index(right);
+    fn find_index(&mut self, name: &str) -> Result<usize, String> {
+        self.names
+            .intern(name)
+            .ok_or_else(|| format!("Invalid computer name: {}", name))
+    }
 
-        self.nodes[left].peers.insert(right);
-        self.nodes[right].peers.insert(left);
+    fn add_peer(&mut self, left: &str, right: &str) -> Result<(), String> {
+        let left = self.find_index(left)?;
+        let right = self.find_index(right)?;
+        self.edges.push((left, right));
+        Ok(())
     }
 }
 
-fn build_graph(path: String, capacity: usize) -> Result<Graph, Box<dyn Error>> {
-    let mut builder = GraphBuilder::new(capacity);
+fn build_graph(path: String) -> Result<Graph, Box<dyn Error>> {
+    let mut builder = GraphBuilder::new();
 
     for line in BufReader::new(File::open(path)?).lines() {
         let line = line?;
-        let (left, right) = line.split_once('-').ok_or_else(|| "Invalid line format")?;
-        builder.add_peer(left, right);
+        let (left, right) = line.split_once('-').ok_or("Invalid line format")?;
+        builder.add_peer(left, right)?;
     }
     Ok(builder.build())
 }
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
+use aoc::intern::{Interner, Packed, Symbols};
 use clap::Parser;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -47,7 +48,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+type WireName = Packed<3>;
+
 struct Circuit {
+    names: Symbols<WireName>,
     wires: Vec<Wire>,
     gates: Vec<Gate>,
     x_ids: Vec<WireId>,
@@ -84,7 +88,7 @@ impl CircuitState {
         }
     }
 
-    fn get_wires(&self, ids: &Vec<WireId>) -> Option<u64> {
+    fn get_wires(&self, ids: &[WireId]) -> Option<u64> {
         let mut value = 0;
 
         for (order, id) in ids.iter().enumerate() {
@@ -98,7 +102,7 @@ impl CircuitState {
         Some(value)
     }
 
-    fn set_wires(&mut self, ids: &Vec<WireId>, value: u64) {
+    fn set_wires(&mut self, ids: &[WireId], value: u64) {
         for (order, id) in ids.iter().enumerate() {
             self.set(id, ((value >> order) & 0x1) as u8);
         }
@@ -115,8 +119,8 @@ impl Circuit {
         state.set_wires(&self.y_ids, y);
 
         let mut wire_set = WireSet::new();
-        wire_set.extend(self.x_ids.iter().map(|&x| x));
-        wire_set.extend(self.y_ids.iter().map(|&y| y));
+        wire_set.extend(self.x_ids.iter().copied());
+        wire_set.extend(self.y_ids.iter().copied());
 
         let mut gate_set = GateSet::new();
 
@@ -203,15 +207,13 @@ impl Gate {
 }
 
 struct Wire {
-    name: String,
     input: Option<GateId>,
     output: Vec<GateId>,
 }
 
 impl Wire {
-    fn new(name: &str) -> Self {
+    fn new() -> Self {
         Wire {
-            name: name.to_owned(),
             input: None,
             output: Vec::new(),
         }
@@ -219,7 +221,7 @@ impl Wire {
 }
 
 struct CircuitBuilder {
-    wire_names: HashMap<String, WireId>,
+    wire_names: Interner<WireName>,
     wires: Vec<Wire>,
     gates: Vec<Gate>,
     x: u64,
@@ -229,7 +231,7 @@ struct CircuitBuilder {
 impl CircuitBuilder {
     fn new() -> Self {
         CircuitBuilder {
-            wire_names: HashMap::new(),
+            wire_names: Interner::new(),
             wires: Vec::new(),
             gates: Vec::new(),
             x: 0,
@@ -237,15 +239,15 @@ impl CircuitBuilder {
         }
     }
 
-    fn get_wire_id(&mut self, name: &str) -> WireId {
-        if let Some(&id) = self.wire_names.get(name) {
-            id
-        } else {
-            let id = WireId(self.wires.len());
-            self.wires.push(Wire::new(name));
-            self.wire_names.insert(name.to_owned(), id);
-            id
+    fn get_wire_id(&mut self, name: &str) -> Result<WireId, String> {
+        let id = self
+            .wire_names
+            .intern(name)
+            .ok_or_else(|| format!("Invalid wire name: {}", name))?;
+        if id == self.wires.len() {
+            self.wires.push(Wire::new());
         }
+        Ok(WireId(id))
     }
 
     fn add_wire(&mut self, name: &str, state: &str) -> Result<(), Box<dyn Error>> {
@@ -263,14 +265,14 @@ impl CircuitBuilder {
             self.y += value;
         }
 
-        let _ = self.get_wire_id(name);
+        self.get_wire_id(name)?;
         Ok(())
     }
 
     fn add_gate(&mut self, left: &str, op: &str, right: &str, out: &str) -> Result<(), String> {
-        let left = self.get_wire_id(left);
-        let right = self.get_wire_id(right);
-        let out = self.get_wire_id(out);
+        let left = self.get_wire_id(left)?;
+        let right = self.get_wire_id(right)?;
+        let out = self.get_wire_id(out)?;
 
         let op = match op {
             "AND" => GateOp::And,
@@ -291,23 +293,14 @@ impl CircuitBuilder {
         Ok(())
     }
 
-    fn wire_ids(&self, ch: char) -> Vec<WireId> {
-        let mut ids: Vec<_> = self
-            .wires
-            .iter()
-            .enumerate()
-            .filter(|(_, wire)| wire.name.starts_with(ch))
-            .collect();
-        ids.sort_by_key(|(_, wire)| &wire.name);
-        ids.iter().map(|(id, _)| WireId(*id)).collect()
-    }
-
     fn build(self) -> (Circuit, u64, u64) {
-        let x_ids = self.wire_ids('x');
-        let y_ids = self.wire_ids('y');
-        let z_ids = self.wire_ids('z');
+        let names = self.wire_names.freeze();
+        let x_ids = wire_ids(&names, 'x');
+        let y_ids = wire_ids(&names, 'y');
+        let z_ids = wire_ids(&names, 'z');
         (
             Circuit {
+                names,
                 wires: self.wires,
                 gates: self.gates,
                 x_ids,
@@ -320,6 +313,15 @@ impl CircuitBuilder {
     }
 }
 
+fn wire_ids(names: &Symbols<WireName>, ch: char) -> Vec<WireId> {
+    let mut ids: Vec<_> = names
+        .iter()
+        .filter(|(_, name)| name.starts_with(ch))
+        .collect();
+    ids.sort_by_key(|&(_, name)| name);
+    ids.iter().map(|&(id, _)| WireId(id)).collect()
+}
+
 fn parse_file(path: String) -> Result<(Circuit, u64, u64), Box<dyn Error>> {
     let wire_re = Regex::new(r"(.*): (\d)")?;
     let comp_re = Regex::new(r"(\w+) (\w+) (\w+) -> (\w+)")?;
@@ -0,0 +1,120 @@
+use crate::registry::{Effect, Registry};
+use crate::{Evaluator, NearMiss, Scanner};
+use std::collections::BTreeMap;
+
+const RESET: &str = "\x1b[0m";
+const ENABLED: &str = "\x1b[1;32m";
+const DISABLED: &str = "\x1b[31m";
+const ENABLE: &str = "\x1b[1;4;36m";
+const DISABLE: &str = "\x1b[1;4;35m";
+const DIM: &str = "\x1b[2m";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Enabled,
+    Disabled,
+    Enable,
+    Disable,
+    NearMiss,
+}
+
+impl Style {
+    fn code(&self) -> &'static str {
+        match self {
+            Style::Plain => "",
+            Style::Enabled => ENABLED,
+            Style::Disabled => DISABLED,
+            Style::Enable => ENABLE,
+            Style::Disable => DISABLE,
+            Style::NearMiss => DIM,
+        }
+    }
+}
+
+/// Prints `src` with recognized instructions highlighted and near misses dimmed, followed
+/// by a list of the near misses and a count of them by reason.
+pub fn annotate(src: &str, registry: &Registry) {
+    let mut styles = vec![Style::Plain; src.len()];
+    let mut near_misses: Vec<NearMiss> = Vec::new();
+    let mut eval = Evaluator::new();
+
+    for candidate in Scanner::new(src, registry).candidates() {
+        match candidate {
+            Ok(token) => {
+                let style = match token.instr.spec.effect {
+                    Effect::Value(_) if eval.enabled => Style::Enabled,
+                    Effect::Value(_) => Style::Disabled,
+                    Effect::Enable => Style::Enable,
+                    Effect::Disable => Style::Disable,
+                    Effect::Toggle if eval.enabled => Style::Disable,
+                    Effect::Toggle => Style::Enable,
+                };
+                // Overflow only affects the totals, which aren't shown here:
+                let _ = eval.apply(&token.instr);
+                styles[token.offset..token.offset + token.len].fill(style);
+            }
+            Err(miss) => near_misses.push(miss),
+        }
+    }
+
+    // Recognized instructions win over any near miss they overlap:
+    let mut markers: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (idx, miss) in near_misses.iter().enumerate() {
+        for style in &mut styles[miss.offset..miss.offset + miss.len] {
+            if *style == Style::Plain {
+                *style = Style::NearMiss;
+            }
+        }
+        markers
+            .entry(miss.offset + miss.len)
+            .or_default()
+            .push(idx + 1);
+    }
+
+    let mut out = String::with_capacity(src.len() * 2);
+    let mut current = Style::Plain;
+    for (pos, ch) in src.char_indices() {
+        if let Some(nums) = markers.get(&pos) {
+            out.push_str(&format!("{}{}", RESET, marker(nums)));
+            out.push_str(current.code());
+        }
+        if styles[pos] != current {
+            out.push_str(RESET);
+            out.push_str(styles[pos].code());
+            current = styles[pos];
+        }
+        out.push(ch);
+    }
+    if let Some(nums) = markers.get(&src.len()) {
+        out.push_str(&format!("{}{}", RESET, marker(nums)));
+    }
+    out.push_str(RESET);
+    println!("{}", out.trim_end());
+
+    println!();
+    println!("Near misses:");
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for (idx, miss) in near_misses.iter().enumerate() {
+        let text = &src[miss.offset..miss.offset + miss.len];
+        println!(
+            "  [{}] offset {}: {:?} - {}",
+            idx + 1,
+            miss.offset,
+            text,
+            miss.reason
+        );
+        *counts.entry(miss.reason.kind()).or_default() += 1;
+    }
+
+    println!();
+    println!("Rejected candidates by reason:");
+    for (kind, count) in counts {
+        println!("  {:<22} {}", kind, count);
+    }
+}
+
+fn marker(nums: &[usize]) -> String {
+    let nums: Vec<String> = nums.iter().map(|n| n.to_string()).collect();
+    format!("{}[{}]{}", DIM, nums.join(","), RESET)
+}
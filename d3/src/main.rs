@@ -1,104 +1,452 @@
-use std::env;
+use clap::Parser;
+use registry::{Effect, Registry, Spec};
+use std::error::Error;
+use std::fmt;
 use std::fs;
 
-fn main() {
-    let path = env::args().nth(1).unwrap();
-    let message = fs::read_to_string(path).unwrap();
-    let mut parser = Parser::new();
-    let mut sm_do = StrMatch::new("do()");
-    let mut sm_dont = StrMatch::new("don't()");
-    let mut enable = true;
-    let mut sum = 0;
-    for ch in message.chars() {
-        if true == sm_do.push_char(ch) {
-            println!("do()");
-            enable = true;
-        }
-        if true == sm_dont.push_char(ch) {
-            println!("don't()");
-            enable = false;
-        }
-        if let Some((a, b)) = parser.push_char(ch) {
-            if enable {
-                println!("mul({},{})", a, b);
-                sum += a * b;
-            }
+mod annotate;
+mod registry;
+
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Instruction set config (defaults to mul, do and don't)
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// Print the memory with instructions highlighted and near misses explained
+    #[arg(short, long)]
+    annotate: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let registry = match &cli.config {
+        Some(path) => Registry::from_file(path)?,
+        None => Registry::default(),
+    };
+    let message = fs::read_to_string(&cli.file)?;
+    if cli.annotate {
+        annotate::annotate(&message, &registry);
+        println!();
+    }
+
+    let mut eval = Evaluator::new();
+    for token in Scanner::new(&message, &registry) {
+        let value = eval.apply(&token.instr).ok_or_else(|| {
+            format!(
+                "overflow evaluating {} at bytes {}..{}",
+                token.instr,
+                token.offset,
+                token.offset + token.len
+            )
+        })?;
+        if !cli.annotate && (eval.enabled || value.is_none()) {
+            println!("{}", token.instr);
         }
     }
-    println!("Sum: {}", sum);
+    println!("Sum: {}", eval.total);
+    println!("Enabled Sum: {}", eval.enabled_total);
+    Ok(())
 }
 
-struct Parser {
-    next: char,
-    num: Option<u32>,
-    save: Option<u32>,
+#[derive(Debug, Clone)]
+struct Instruction<'r> {
+    spec: &'r Spec,
+    args: Vec<i64>,
 }
 
-impl Parser {
-    fn new() -> Self {
-        Parser {
-            next: 'm',
-            num: None,
-            save: None,
-        }
-    }
-
-    fn push_char(&mut self, c: char) -> Option<(u32, u32)> {
-        let mut result = None;
-        self.next = if c == 'm' {
-            self.num = None;
-            self.save = None;
-            'u'
-        } else if c == self.next {
-            match c {
-                'u' => 'l',
-                'l' => '(',
-                '(' => ',',
-                ',' if self.num.is_some() => {
-                    self.save = self.num;
-                    self.num = None;
-                    ')'
-                }
-                ')' if self.num.is_some() => {
-                    result = Some((self.save.unwrap(), self.num.unwrap()));
-                    'm'
-                }
-                _ => 'm',
+impl fmt::Display for Instruction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.spec.name, args.join(","))
+    }
+}
+
+/// An instruction and the byte range it occupies in the source.
+#[derive(Debug)]
+struct Token<'r> {
+    offset: usize,
+    len: usize,
+    instr: Instruction<'r>,
+}
+
+/// Finds the valid instructions in corrupted memory.
+///
+/// Each position is tried as the start of an instruction, so a failed match never hides
+/// an instruction that begins inside it. If several instructions match at one position the
+/// longest wins.
+struct Scanner<'a, 'r> {
+    src: &'a [u8],
+    registry: &'r Registry,
+    pos: usize,
+}
+
+impl<'a, 'r> Scanner<'a, 'r> {
+    fn new(src: &'a str, registry: &'r Registry) -> Self {
+        Scanner {
+            src: src.as_bytes(),
+            registry,
+            pos: 0,
+        }
+    }
+
+    /// Also yields near misses: places where an instruction name is followed by `(` or
+    /// whitespace but the rest doesn't parse.
+    fn candidates(self) -> Candidates<'a, 'r> {
+        Candidates(self)
+    }
+
+    fn candidate_at(&self, start: usize) -> Option<Result<Token<'r>, NearMiss>> {
+        let mut best: Option<Result<Token<'r>, NearMiss>> = None;
+        for spec in &self.registry.specs {
+            let mut cursor = Cursor {
+                src: self.src,
+                pos: start,
+            };
+            let candidate = match cursor.instruction(spec) {
+                Ok(args) => Ok(Token {
+                    offset: start,
+                    len: cursor.pos - start,
+                    instr: Instruction { spec, args },
+                }),
+                Err(Reject::NoName) => continue,
+                Err(Reject::At(pos, reason)) => Err(NearMiss {
+                    offset: start,
+                    len: pos - start,
+                    reason,
+                }),
+            };
+            best = match (best, candidate) {
+                (Some(Ok(a)), Ok(b)) => Some(Ok(if b.len > a.len { b } else { a })),
+                (Some(Ok(a)), Err(_)) | (Some(Err(_)), Ok(a)) => Some(Ok(a)),
+                (Some(Err(a)), Err(b)) => Some(Err(if b.len > a.len { b } else { a })),
+                (None, candidate) => Some(candidate),
+            };
+        }
+        best
+    }
+
+    fn next_candidate(&mut self) -> Option<Result<Token<'r>, NearMiss>> {
+        while self.pos < self.src.len() {
+            let start = self.pos;
+            let candidate = self.candidate_at(start);
+            self.pos += match &candidate {
+                Some(Ok(token)) => token.len,
+                _ => 1,
+            };
+            if candidate.is_some() {
+                return candidate;
+            }
+        }
+        None
+    }
+}
+
+impl<'r> Iterator for Scanner<'_, 'r> {
+    type Item = Token<'r>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Ok(token) = self.next_candidate()? {
+                return Some(token);
             }
-        } else if c.is_ascii_digit() && (self.next == ',' || self.next == ')') {
-            let value = c.to_digit(10).unwrap();
-            self.num = Some(self.num.unwrap_or(0) * 10 + value);
-            self.next
+        }
+    }
+}
+
+struct Candidates<'a, 'r>(Scanner<'a, 'r>);
+
+impl<'r> Iterator for Candidates<'_, 'r> {
+    type Item = Result<Token<'r>, NearMiss>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_candidate()
+    }
+}
+
+/// Text that starts like an instruction but was rejected.
+#[derive(Debug, PartialEq, Eq)]
+struct NearMiss {
+    offset: usize,
+    len: usize,
+    reason: Reason,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Reason {
+    Whitespace,
+    MissingParen,
+    Unexpected(char),
+    DigitCount(usize),
+    OutOfRange(i64),
+    TooFewArgs,
+    TooManyArgs,
+    EndOfInput,
+}
+
+impl Reason {
+    /// The reason without its details, for grouping.
+    fn kind(&self) -> &'static str {
+        match self {
+            Reason::Whitespace => "whitespace",
+            Reason::MissingParen => "missing '('",
+            Reason::Unexpected(_) => "unexpected character",
+            Reason::DigitCount(_) => "wrong digit count",
+            Reason::OutOfRange(_) => "value out of range",
+            Reason::TooFewArgs => "too few arguments",
+            Reason::TooManyArgs => "too many arguments",
+            Reason::EndOfInput => "end of input",
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Unexpected(c) => write!(f, "unexpected {:?}", c),
+            Reason::DigitCount(n) => write!(f, "{} digits", n),
+            Reason::OutOfRange(v) => write!(f, "{} out of range", v),
+            _ => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+enum Reject {
+    /// The name itself didn't match, so this isn't a candidate at all.
+    NoName,
+    At(usize, Reason),
+}
+
+struct Cursor<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn literal(&mut self, s: &[u8]) -> bool {
+        if self.src[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            true
         } else {
-            'm'
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn reject(&self, expected: Reason) -> Reject {
+        let reason = match self.peek() {
+            None => Reason::EndOfInput,
+            Some(b) if b.is_ascii_whitespace() => Reason::Whitespace,
+            _ => expected,
         };
-        result
+        Reject::At(self.pos, reason)
+    }
+
+    fn unexpected(&self) -> Reject {
+        self.reject(Reason::Unexpected(self.peek().map_or(' ', char::from)))
+    }
+
+    fn instruction(&mut self, spec: &Spec) -> Result<Vec<i64>, Reject> {
+        if !self.literal(spec.name.as_bytes()) {
+            return Err(Reject::NoName);
+        }
+        if !self.literal(b"(") {
+            return match self.peek() {
+                // Part of a longer word, such as the `do` in `don't`:
+                Some(b) if b.is_ascii_alphanumeric() || b == b'\'' => Err(Reject::NoName),
+                _ => Err(self.reject(Reason::MissingParen)),
+            };
+        }
+
+        let mut args = Vec::with_capacity(spec.arity);
+        for idx in 0..spec.arity {
+            if idx > 0 && !self.literal(b",") {
+                return Err(match self.peek() {
+                    Some(b')') => Reject::At(self.pos, Reason::TooFewArgs),
+                    _ => self.unexpected(),
+                });
+            }
+            args.push(self.number(spec)?);
+        }
+
+        if self.literal(b")") {
+            Ok(args)
+        } else {
+            Err(match self.peek() {
+                Some(b',') => Reject::At(self.pos, Reason::TooManyArgs),
+                Some(b) if spec.arity == 0 && b.is_ascii_digit() => {
+                    Reject::At(self.pos, Reason::TooManyArgs)
+                }
+                _ => self.unexpected(),
+            })
+        }
+    }
+
+    fn number(&mut self, spec: &Spec) -> Result<i64, Reject> {
+        let start = self.pos;
+        let negative = spec.signed && self.literal(b"-");
+        let digits = self.src[self.pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Err(self.unexpected());
+        }
+        if !spec.digits.contains(&digits) {
+            return Err(Reject::At(start, Reason::DigitCount(digits)));
+        }
+        let value = self.src[self.pos..self.pos + digits]
+            .iter()
+            .fold(0, |acc, b| acc * 10 + (b - b'0') as i64);
+        let value = if negative { -value } else { value };
+        if spec.values.as_ref().is_some_and(|r| !r.contains(&value)) {
+            return Err(Reject::At(start, Reason::OutOfRange(value)));
+        }
+        self.pos += digits;
+        Ok(value)
     }
 }
 
-struct StrMatch {
-    v: Vec<char>,
-    i: usize,
+/// Runs instructions: `total` accumulates every value, `enabled_total` only those produced
+/// while enabled.
+struct Evaluator {
+    enabled: bool,
+    total: i64,
+    enabled_total: i64,
 }
 
-impl StrMatch {
-    fn new(s: &str) -> Self {
-        StrMatch {
-            v: s.chars().collect(),
-            i: 0,
+impl Evaluator {
+    fn new() -> Self {
+        Evaluator {
+            enabled: true,
+            total: 0,
+            enabled_total: 0,
         }
     }
 
-    fn push_char(&mut self, c: char) -> bool {
-        if c == self.v[self.i] {
-            self.i += 1;
-            if self.i == self.v.len() {
-                self.i = 0;
-                return true;
+    /// Returns the instruction's value, if it has one, or `None` on overflow.
+    fn apply(&mut self, instr: &Instruction) -> Option<Option<i64>> {
+        match instr.spec.effect {
+            Effect::Enable => self.enabled = true,
+            Effect::Disable => self.enabled = false,
+            Effect::Toggle => self.enabled = !self.enabled,
+            Effect::Value(op) => {
+                let value = op.apply(&instr.args)?;
+                self.total = self.total.checked_add(value)?;
+                if self.enabled {
+                    self.enabled_total = self.enabled_total.checked_add(value)?;
+                }
+                return Some(Some(value));
             }
-        } else {
-            self.i = 0;
         }
-        false
+        Some(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instructions(src: &str, registry: &Registry) -> Vec<(usize, String)> {
+        Scanner::new(src, registry)
+            .map(|t| (t.offset, t.instr.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_scanner() {
+        let src = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let registry = Registry::default();
+        let expected = [
+            (1, "mul(2,4)"),
+            (20, "don't()"),
+            (28, "mul(5,5)"),
+            (48, "mul(11,8)"),
+            (59, "do()"),
+            (64, "mul(8,5)"),
+        ];
+        assert_eq!(
+            instructions(src, &registry),
+            expected.map(|(o, s)| (o, s.to_owned()))
+        );
+
+        let mut eval = Evaluator::new();
+        Scanner::new(src, &registry).for_each(|t| {
+            eval.apply(&t.instr);
+        });
+        assert_eq!((eval.total, eval.enabled_total), (161, 48));
+    }
+
+    #[test]
+    fn test_overlapping_prefix() {
+        let registry = Registry::default();
+        let found = |src| instructions(src, &registry);
+        assert_eq!(found("dodo()"), vec![(2, "do()".to_owned())]);
+        assert_eq!(found("mulmul(1,2)"), vec![(3, "mul(1,2)".to_owned())]);
+        assert_eq!(
+            found("mul(1234,5)mul(123,4)"),
+            vec![(11, "mul(123,4)".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_near_misses() {
+        let registry = Registry::default();
+        let src = "mul(4*mul ( 2 , 4 )mul[3,7]mul(1234,5)mul(1)mul(1,2,3)do(1)dont()mul(";
+        let misses: Vec<(usize, Reason)> = Scanner::new(src, &registry)
+            .candidates()
+            .filter_map(|c| c.err())
+            .map(|m| (m.offset, m.reason))
+            .collect();
+        assert_eq!(
+            misses,
+            vec![
+                (0, Reason::Unexpected('*')),
+                (6, Reason::Whitespace),
+                (19, Reason::MissingParen),
+                (27, Reason::DigitCount(4)),
+                (38, Reason::TooFewArgs),
+                (44, Reason::TooManyArgs),
+                (54, Reason::TooManyArgs),
+                (65, Reason::EndOfInput),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let registry = Registry::parse(
+            "# custom set\n\
+             add 2 add digits=1-4\n\
+             neg 1 neg signed values=-99..99\n\
+             flip 0 toggle\n",
+        )
+        .unwrap();
+        let src = "add(1000,2)neg(-5)neg(100)flip()add(1,1)mul(2,3)";
+        assert_eq!(
+            instructions(src, &registry),
+            vec![
+                (0, "add(1000,2)".to_owned()),
+                (11, "neg(-5)".to_owned()),
+                (26, "flip()".to_owned()),
+                (32, "add(1,1)".to_owned()),
+            ]
+        );
+
+        let mut eval = Evaluator::new();
+        for token in Scanner::new(src, &registry) {
+            eval.apply(&token.instr);
+        }
+        assert_eq!((eval.total, eval.enabled_total), (1009, 1007));
+
+        assert!(Registry::parse("mul 2 frobnicate").is_err());
+        assert!(Registry::parse("mul 2 mul\nmul 1 neg").is_err());
+        assert!(Registry::parse("neg 0 neg").is_err());
     }
 }
@@ -0,0 +1,188 @@
+use std::fs;
+use std::ops::RangeInclusive;
+
+/// What an instruction does when evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Enable,
+    Disable,
+    Toggle,
+    /// Adds the operation's result to the accumulators.
+    Value(Op),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Mul,
+    Add,
+    Sub,
+    Neg,
+    Min,
+    Max,
+}
+
+impl Op {
+    pub fn apply(&self, args: &[i64]) -> Option<i64> {
+        let mut iter = args.iter().copied();
+        match self {
+            Op::Mul => iter.try_fold(1i64, |acc, v| acc.checked_mul(v)),
+            Op::Add => iter.try_fold(0i64, |acc, v| acc.checked_add(v)),
+            Op::Sub => {
+                let first = iter.next()?;
+                iter.try_fold(first, |acc, v| acc.checked_sub(v))
+            }
+            Op::Neg => iter.try_fold(0i64, |acc, v| acc.checked_sub(v)),
+            Op::Min => iter.min(),
+            Op::Max => iter.max(),
+        }
+    }
+}
+
+/// Declares one instruction: `name(arg,...)` with `arity` numeric arguments.
+#[derive(Debug, Clone)]
+pub struct Spec {
+    pub name: String,
+    pub arity: usize,
+    /// Allowed digit count of each argument.
+    pub digits: RangeInclusive<usize>,
+    /// Whether arguments may have a leading `-`.
+    pub signed: bool,
+    /// Allowed argument values.
+    pub values: Option<RangeInclusive<i64>>,
+    pub effect: Effect,
+}
+
+impl Spec {
+    pub fn new(name: &str, arity: usize, effect: Effect) -> Self {
+        Spec {
+            name: name.to_owned(),
+            arity,
+            digits: 1..=3,
+            signed: false,
+            values: None,
+            effect,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    pub specs: Vec<Spec>,
+}
+
+impl Default for Registry {
+    /// The puzzle's instruction set: `mul(a,b)`, `do()` and `don't()`.
+    fn default() -> Self {
+        Registry {
+            specs: vec![
+                Spec::new("mul", 2, Effect::Value(Op::Mul)),
+                Spec::new("do", 0, Effect::Enable),
+                Spec::new("don't", 0, Effect::Disable),
+            ],
+        }
+    }
+}
+
+impl Registry {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let config = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&config)
+    }
+
+    /// Parses a config with one instruction per line:
+    ///
+    /// ```text
+    /// # name  arity  effect  [digits=MIN-MAX] [values=MIN..MAX] [signed]
+    /// mul     2      mul
+    /// do      0      enable
+    /// don't   0      disable
+    /// neg     1      neg     signed
+    /// ```
+    ///
+    /// Effects are `enable`, `disable`, `toggle`, or one of the value operations `mul`, `add`,
+    /// `sub`, `neg`, `min` and `max`.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut specs = Vec::new();
+        for (num, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let spec = parse_spec(line).map_err(|e| format!("line {}: {}", num + 1, e))?;
+            if specs.iter().any(|s: &Spec| s.name == spec.name) {
+                return Err(format!(
+                    "line {}: duplicate instruction {}",
+                    num + 1,
+                    spec.name
+                ));
+            }
+            specs.push(spec);
+        }
+
+        if specs.is_empty() {
+            return Err("no instructions declared".into());
+        }
+        Ok(Registry { specs })
+    }
+}
+
+fn parse_spec(line: &str) -> Result<Spec, String> {
+    let mut tokens = line.split_ascii_whitespace();
+    let (Some(name), Some(arity), Some(effect)) = (tokens.next(), tokens.next(), tokens.next())
+    else {
+        return Err("expected: <name> <arity> <effect> [options]".into());
+    };
+
+    if name.contains(['(', ')', ',']) {
+        return Err(format!("invalid name {:?}", name));
+    }
+    let arity = arity
+        .parse::<usize>()
+        .map_err(|_| format!("invalid arity {:?}", arity))?;
+    let effect = match effect {
+        "enable" => Effect::Enable,
+        "disable" => Effect::Disable,
+        "toggle" => Effect::Toggle,
+        "mul" => Effect::Value(Op::Mul),
+        "add" => Effect::Value(Op::Add),
+        "sub" => Effect::Value(Op::Sub),
+        "neg" => Effect::Value(Op::Neg),
+        "min" => Effect::Value(Op::Min),
+        "max" => Effect::Value(Op::Max),
+        _ => return Err(format!("unknown effect {:?}", effect)),
+    };
+    if matches!(effect, Effect::Value(_)) && arity == 0 {
+        return Err(format!("{} needs at least one argument", name));
+    }
+
+    let mut spec = Spec::new(name, arity, effect);
+    for option in tokens {
+        match option.split_once('=') {
+            None if option == "signed" => spec.signed = true,
+            Some(("digits", range)) => {
+                let (min, max) = parse_range(range, "-")?;
+                if !(1..=18).contains(&min) || !(min..=18).contains(&max) {
+                    return Err(format!("digits must be within 1-18: {:?}", range));
+                }
+                spec.digits = min as usize..=max as usize;
+            }
+            Some(("values", range)) => {
+                let (min, max) = parse_range(range, "..")?;
+                spec.values = Some(min..=max);
+            }
+            _ => return Err(format!("unknown option {:?}", option)),
+        }
+    }
+    Ok(spec)
+}
+
+fn parse_range(range: &str, sep: &str) -> Result<(i64, i64), String> {
+    let invalid = || format!("invalid range {:?}", range);
+    let (min, max) = range.split_once(sep).ok_or_else(invalid)?;
+    let min = min.parse::<i64>().map_err(|_| invalid())?;
+    let max = max.parse::<i64>().map_err(|_| invalid())?;
+    if min > max {
+        return Err(invalid());
+    }
+    Ok((min, max))
+}
@@ -1,48 +1,142 @@
 #![allow(dead_code)]
 
-use std::env;
-use std::fs::File;
+use clap::{Parser, ValueEnum};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use stencil::Stencil;
 
-fn main() {
-    let path = env::args().nth(1).unwrap();
-    let m = Matrix::new(&path);
-    let (x, y) = m.dimensions();
-    let mut count = 0;
-    for j in 0..y {
-        for i in 0..x {
-            count += xmas_search(&m, Point { x: i, y: j });
-        }
-    }
-    println!("Count: {}", count);
+mod render;
+mod stencil;
+
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Words to search for (defaults to XMAS)
+    #[arg(short, long = "word", value_name = "WORD")]
+    words: Vec<String>,
+
+    /// Comma separated directions to search (defaults to all eight)
+    #[arg(short, long, value_delimiter = ',')]
+    dirs: Vec<Dir>,
+
+    /// Stencil file to match in every orientation, with `.` as a wildcard (defaults to X-MAS)
+    #[arg(short, long)]
+    pattern: Option<String>,
+
+    /// List every match
+    #[arg(short, long)]
+    list: bool,
+
+    /// Print the grid with the letters of the shown matches highlighted
+    #[arg(short, long, value_enum)]
+    render: Option<Render>,
+
+    /// Write the grid with the shown matches highlighted to an .html or .svg file
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Which matches to render
+    #[arg(short, long, value_enum, default_value_t = Show::Words)]
+    show: Show,
 }
 
-fn xmas_search(m: &Matrix, point: Point) -> usize {
-    if m.get(point) == 'A' {
-        let mut v1 = 0;
-        let mut v2 = 0;
+#[derive(Clone, Copy, ValueEnum)]
+enum Render {
+    /// Replace letters outside any match with `.`
+    Plain,
+    /// Colour each match
+    Color,
+}
 
-        v1 |= ms_search(m, point, Dir::NE);
-        v1 |= ms_search(m, point, Dir::SW);
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Show {
+    Words,
+    Stencil,
+}
 
-        v2 |= ms_search(m, point, Dir::SE);
-        v2 |= ms_search(m, point, Dir::NW);
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let m = Matrix::new(&cli.file);
 
-        if v1 == 3 && v2 == 3 { 1 } else { 0 }
+    let words = if cli.words.is_empty() {
+        vec!["XMAS".to_owned()]
+    } else {
+        cli.words
+    };
+    let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+    let dirs = if cli.dirs.is_empty() {
+        Dir::ALL.to_vec()
     } else {
-        0
+        cli.dirs
+    };
+
+    let word_matches = m.find_words(&words, &dirs);
+    for (idx, word) in words.iter().enumerate() {
+        let count = word_matches.iter().filter(|m| m.word == idx).count();
+        println!("{}: {}", word, count);
+    }
+    if cli.list {
+        for found in &word_matches {
+            println!(
+                "  {} at ({}, {}) {:?}",
+                words[found.word], found.start.x, found.start.y, found.dir
+            );
+        }
     }
-}
 
-fn ms_search(m: &Matrix, point: Point, dir: Dir) -> u8 {
-    match m.get_target(point, dir, 1) {
-        Some(x) if x == 'M' => 0x1,
-        Some(x) if x == 'S' => 0x2,
-        _ => 0x0,
+    let (name, stencil) = match &cli.pattern {
+        Some(path) => (path.as_str(), Stencil::from_file(path)?),
+        None => ("X-MAS", Stencil::parse(stencil::XMAS)?),
+    };
+    let variants = stencil.variants();
+    let stencil_matches = m.find_stencil(&variants);
+    println!("{}: {}", name, stencil_matches.len());
+    if cli.list {
+        for found in &stencil_matches {
+            println!(
+                "  at ({}, {}) orientation {}",
+                found.origin.x, found.origin.y, found.variant
+            );
+        }
+    }
+
+    if cli.render.is_none() && cli.output.is_none() {
+        return Ok(());
+    }
+    let highlights: Vec<Vec<Point>> = match cli.show {
+        Show::Words => word_matches
+            .iter()
+            .map(|found| m.word_cells(found, words[found.word].chars().count()))
+            .collect(),
+        Show::Stencil => stencil_matches
+            .iter()
+            .map(|found| found.cells(&variants[found.variant]))
+            .collect(),
+    };
+    match cli.render {
+        Some(Render::Plain) => print!("{}", render::plain(&m, &highlights)),
+        Some(Render::Color) => print!("{}", render::ansi(&m, &highlights)),
+        None => {}
     }
+    if let Some(path) = &cli.output {
+        let doc = if path.ends_with(".html") {
+            render::html(&m, &highlights)
+        } else if path.ends_with(".svg") {
+            render::svg(&m, &highlights)
+        } else {
+            return Err(format!("{}: expected an .html or .svg file", path).into());
+        };
+        fs::write(path, doc).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(())
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum Dir {
     N,
     NE,
@@ -54,12 +148,63 @@ enum Dir {
     NW,
 }
 
-#[derive(Clone, Copy)]
+impl Dir {
+    const ALL: [Dir; 8] = [
+        Dir::N,
+        Dir::NE,
+        Dir::E,
+        Dir::SE,
+        Dir::S,
+        Dir::SW,
+        Dir::W,
+        Dir::NW,
+    ];
+}
+
+impl FromStr for Dir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dir::ALL
+            .into_iter()
+            .find(|d| format!("{:?}", d).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("invalid direction: {}", s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct Point {
     x: usize,
     y: usize,
 }
 
+/// `words[word]` found reading from `start` towards `dir`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Match {
+    start: Point,
+    dir: Dir,
+    word: usize,
+}
+
+/// `variants[variant]` found with its top left corner at `origin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct StencilMatch {
+    origin: Point,
+    variant: usize,
+}
+
+impl StencilMatch {
+    fn cells(&self, stencil: &Stencil) -> Vec<Point> {
+        stencil
+            .letters()
+            .map(|(dx, dy, _)| Point {
+                x: self.origin.x + dx,
+                y: self.origin.y + dy,
+            })
+            .collect()
+    }
+}
+
 struct Matrix {
     max_x: usize,
     max_y: usize,
@@ -123,4 +268,157 @@ impl Matrix {
     fn get(&self, point: Point) -> char {
         self.m[point.y][point.x]
     }
+
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.max_y).flat_map(|y| (0..self.max_x).map(move |x| Point { x, y }))
+    }
+
+    /// The cells covered by a word match of length `len`.
+    fn word_cells(&self, found: &Match, len: usize) -> Vec<Point> {
+        (0..len)
+            .filter_map(|i| self.target(found.start, found.dir, i))
+            .collect()
+    }
+
+    fn matches_at(&self, word: &[char], start: Point, dir: Dir) -> bool {
+        word.iter()
+            .enumerate()
+            .all(|(len, &ch)| self.get_target(start, dir, len) == Some(ch))
+    }
+
+    /// Finds every occurrence of each word reading in any of `dirs`, in row-major order of
+    /// the starting point.
+    ///
+    /// A word that reads the same in both directions (or a single letter) covers the same
+    /// cells from either end; each such occurrence is only reported once.
+    fn find_words(&self, words: &[&str], dirs: &[Dir]) -> Vec<Match> {
+        let words: Vec<Vec<char>> = words.iter().map(|w| w.chars().collect()).collect();
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+
+        for start in self.points() {
+            for (idx, word) in words.iter().enumerate() {
+                if word.is_empty() {
+                    continue;
+                }
+                for &dir in dirs {
+                    if !self.matches_at(word, start, dir) {
+                        continue;
+                    }
+                    let end = self.target(start, dir, word.len() - 1).unwrap();
+                    let ends = if (start.y, start.x) <= (end.y, end.x) {
+                        (start, end)
+                    } else {
+                        (end, start)
+                    };
+                    if seen.insert((idx, ends)) {
+                        results.push(Match {
+                            start,
+                            dir,
+                            word: idx,
+                        });
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    /// Finds every placement of any of `variants`, in row-major order of the origin.
+    ///
+    /// Placements that cover the same letters (possible when wildcards make two variants
+    /// overlap) are only reported once.
+    fn find_stencil(&self, variants: &[Stencil]) -> Vec<StencilMatch> {
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+
+        for origin in self.points() {
+            for (idx, s) in variants.iter().enumerate() {
+                if origin.x + s.width > self.max_x || origin.y + s.height > self.max_y {
+                    continue;
+                }
+                let mut cells: Vec<Point> = Vec::new();
+                let fits = s.letters().all(|(dx, dy, ch)| {
+                    let p = Point {
+                        x: origin.x + dx,
+                        y: origin.y + dy,
+                    };
+                    cells.push(p);
+                    self.get(p) == ch
+                });
+                if fits {
+                    cells.sort_by_key(|p| (p.y, p.x));
+                    if seen.insert(cells) {
+                        results.push(StencilMatch {
+                            origin,
+                            variant: idx,
+                        });
+                    }
+                }
+            }
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A matrix with the given rows, for tests here and in other modules.
+    pub(crate) fn matrix(rows: &[&str]) -> Matrix {
+        let m: Vec<Vec<char>> = rows.iter().map(|r| r.chars().collect()).collect();
+        Matrix {
+            max_x: m[0].len(),
+            max_y: m.len(),
+            m,
+        }
+    }
+
+    #[test]
+    fn test_find_words() {
+        let m = matrix(&["..X...", ".SAMX.", ".A..A.", "XMAS.S", ".X...."]);
+        let found = m.find_words(&["XMAS"], &Dir::ALL);
+        assert_eq!(found.len(), 4);
+        assert!(found.contains(&Match {
+            start: Point { x: 0, y: 3 },
+            dir: Dir::E,
+            word: 0
+        }));
+
+        let found = m.find_words(&["XMAS"], &[Dir::W]);
+        assert_eq!(
+            found,
+            vec![Match {
+                start: Point { x: 4, y: 1 },
+                dir: Dir::W,
+                word: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_palindromes() {
+        let m = matrix(&["ABA", "BXB", "ABA"]);
+        // Each edge once, not once from each end:
+        assert_eq!(m.find_words(&["ABA"], &Dir::ALL).len(), 4);
+        assert_eq!(m.find_words(&["X"], &Dir::ALL).len(), 1);
+        assert_eq!(m.find_words(&["ABA"], &[Dir::W]).len(), 2);
+        assert_eq!(m.find_words(&["AXA"], &Dir::ALL).len(), 2);
+    }
+
+    #[test]
+    fn test_find_stencil() {
+        let m = matrix(&["M.M.S", ".A.A.", "S.S.S", ".A...", "M.M.."]);
+        let xmas = Stencil::parse(stencil::XMAS).unwrap().variants();
+        let found = m.find_stencil(&xmas);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].origin, Point { x: 0, y: 0 });
+
+        // Both rotations of a wildcard-padded pair cover the same cells:
+        let pair = Stencil::parse("AA\n..").unwrap().variants();
+        assert_eq!(pair.len(), 4);
+        let m = matrix(&["AA", ".."]);
+        assert_eq!(m.find_stencil(&pair).len(), 1);
+    }
 }
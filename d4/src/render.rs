@@ -0,0 +1,205 @@
+use crate::{Matrix, Point};
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+const ANSI: [&str; 6] = ["31", "32", "33", "34", "35", "36"];
+const HUES: usize = 12;
+const CELL: usize = 24;
+
+/// The indices of the matches covering each cell, by row and column.
+fn coverage(m: &Matrix, matches: &[Vec<Point>]) -> Vec<Vec<Vec<usize>>> {
+    let mut cover = vec![vec![Vec::new(); m.max_x]; m.max_y];
+    for (idx, cells) in matches.iter().enumerate() {
+        for p in cells {
+            cover[p.y][p.x].push(idx);
+        }
+    }
+    cover
+}
+
+/// Assigns each match the smallest colour not used by an earlier match it overlaps, so
+/// overlapping matches are told apart whenever the palette is large enough.
+fn colours(cover: &[Vec<Vec<usize>>], matches: &[Vec<Point>], palette: usize) -> Vec<usize> {
+    let mut colours: Vec<usize> = Vec::with_capacity(matches.len());
+    for (idx, cells) in matches.iter().enumerate() {
+        let mut used = vec![false; palette];
+        for p in cells {
+            for &other in cover[p.y][p.x].iter().filter(|&&o| o < idx) {
+                used[colours[other]] = true;
+            }
+        }
+        colours.push(used.iter().position(|u| !u).unwrap_or(idx % palette));
+    }
+    colours
+}
+
+fn hsl(colour: usize, lightness: u8) -> String {
+    format!("hsl({}, 70%, {}%)", colour * 360 / HUES, lightness)
+}
+
+fn escape(ch: char) -> String {
+    match ch {
+        '<' => "&lt;".into(),
+        '>' => "&gt;".into(),
+        '&' => "&amp;".into(),
+        _ => ch.to_string(),
+    }
+}
+
+/// The grid with every letter outside a match replaced by `.`.
+pub fn plain(m: &Matrix, matches: &[Vec<Point>]) -> String {
+    let cover = coverage(m, matches);
+    let mut out = String::new();
+    for (y, row) in m.m.iter().enumerate() {
+        for (x, &ch) in row.iter().enumerate() {
+            out.push(if cover[y][x].is_empty() { '.' } else { ch });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The grid with each match in its own colour. Cells shared by several matches take the
+/// colour of the last and are underlined.
+pub fn ansi(m: &Matrix, matches: &[Vec<Point>]) -> String {
+    let cover = coverage(m, matches);
+    let colours = colours(&cover, matches, ANSI.len());
+    let mut out = String::new();
+    for (y, row) in m.m.iter().enumerate() {
+        for (x, &ch) in row.iter().enumerate() {
+            match cover[y][x].as_slice() {
+                [] => write!(out, "\x1b[2m{}{}", ch, RESET),
+                [idx] => write!(out, "\x1b[1;{}m{}{}", ANSI[colours[*idx]], ch, RESET),
+                [.., idx] => write!(out, "\x1b[1;4;{}m{}{}", ANSI[colours[*idx]], ch, RESET),
+            }
+            .unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// An HTML table with each match in its own background colour. Cells shared by several
+/// matches are striped with all of their colours.
+pub fn html(m: &Matrix, matches: &[Vec<Point>]) -> String {
+    let cover = coverage(m, matches);
+    let colours = colours(&cover, matches, HUES);
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<style>\n");
+    out.push_str("table { border-collapse: collapse; font-family: monospace; }\n");
+    out.push_str("td { width: 1.5em; height: 1.5em; text-align: center; color: #bbb; }\n");
+    out.push_str("td.m { color: #000; font-weight: bold; }\n");
+    out.push_str("</style>\n</head>\n<body>\n<table>\n");
+    for (y, row) in m.m.iter().enumerate() {
+        out.push_str("<tr>");
+        for (x, &ch) in row.iter().enumerate() {
+            let fills: Vec<String> = cover[y][x]
+                .iter()
+                .map(|&idx| hsl(colours[idx], 75))
+                .collect();
+            match fills.len() {
+                0 => write!(out, "<td>{}</td>", escape(ch)),
+                1 => write!(
+                    out,
+                    "<td class=\"m\" style=\"background: {}\">{}</td>",
+                    fills[0],
+                    escape(ch)
+                ),
+                _ => write!(
+                    out,
+                    "<td class=\"m\" style=\"background: linear-gradient(135deg, {})\">{}</td>",
+                    fills.join(", "),
+                    escape(ch)
+                ),
+            }
+            .unwrap();
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// An SVG image of the grid with a ring around each letter of each match. Rings of
+/// overlapping matches are nested inside one another.
+pub fn svg(m: &Matrix, matches: &[Vec<Point>]) -> String {
+    let cover = coverage(m, matches);
+    let colours = colours(&cover, matches, HUES);
+    let (width, height) = (m.max_x * CELL, m.max_y * CELL);
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"monospace\" font-size=\"14\" text-anchor=\"middle\">",
+        width, height
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>",
+        width, height
+    )
+    .unwrap();
+
+    for (y, row) in m.m.iter().enumerate() {
+        for (x, &ch) in row.iter().enumerate() {
+            let (cx, cy) = (x * CELL + CELL / 2, y * CELL + CELL / 2);
+            for (layer, &idx) in cover[y][x].iter().enumerate() {
+                let r = (CELL / 2).saturating_sub(1 + 3 * layer).max(3);
+                writeln!(
+                    out,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" \
+                     stroke-width=\"2\"/>",
+                    cx,
+                    cy,
+                    r,
+                    hsl(colours[idx], 45)
+                )
+                .unwrap();
+            }
+            let fill = if cover[y][x].is_empty() {
+                "#bbb"
+            } else {
+                "#000"
+            };
+            writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                cx,
+                cy + 5,
+                fill,
+                escape(ch)
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::matrix;
+
+    fn row(y: usize, xs: std::ops::Range<usize>) -> Vec<Point> {
+        xs.map(|x| Point { x, y }).collect()
+    }
+
+    #[test]
+    fn test_plain() {
+        let m = matrix(&["XMASX", "ABCDE"]);
+        assert_eq!(plain(&m, &[row(0, 0..4)]), "XMAS.\n.....\n");
+    }
+
+    #[test]
+    fn test_overlap_colours() {
+        let m = matrix(&["XMASAMX"]);
+        let matches = [row(0, 0..4), row(0, 3..7), row(0, 6..7)];
+        let cover = coverage(&m, &matches);
+        assert_eq!(cover[0][3], vec![0, 1]);
+        assert_eq!(colours(&cover, &matches, HUES), vec![0, 1, 0]);
+        // Once the palette runs out colours wrap around:
+        assert_eq!(colours(&cover, &matches, 1), vec![0, 0, 0]);
+    }
+}
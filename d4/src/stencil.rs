@@ -0,0 +1,123 @@
+use std::fs;
+
+/// The X-shaped `MAS` from part 2.
+pub const XMAS: &str = "M.S\n.A.\nM.S";
+
+/// A small 2D pattern; `None` cells are wildcards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stencil {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Vec<Option<char>>>,
+}
+
+impl Stencil {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Parses one row per line, with `.` as a wildcard. Short rows are padded with wildcards.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let rows: Vec<&str> = text.trim_end_matches(['\n', '\r']).lines().collect();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        if width == 0 {
+            return Err("empty stencil".into());
+        }
+
+        let cells: Vec<Vec<Option<char>>> = rows
+            .iter()
+            .map(|row| {
+                let mut cells: Vec<Option<char>> =
+                    row.chars().map(|ch| (ch != '.').then_some(ch)).collect();
+                cells.resize(width, None);
+                cells
+            })
+            .collect();
+        if cells.iter().flatten().all(|c| c.is_none()) {
+            return Err("stencil has no letters".into());
+        }
+        Ok(Stencil {
+            width,
+            height: cells.len(),
+            cells,
+        })
+    }
+
+    /// Rotates a quarter turn clockwise.
+    pub fn rotate(&self) -> Stencil {
+        let cells = (0..self.width)
+            .map(|x| (0..self.height).rev().map(|y| self.cells[y][x]).collect())
+            .collect();
+        Stencil {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    /// Mirrors left to right.
+    pub fn reflect(&self) -> Stencil {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        Stencil { cells, ..*self }
+    }
+
+    /// The distinct stencils among all four rotations of the stencil and of its mirror image.
+    pub fn variants(&self) -> Vec<Stencil> {
+        let mut variants: Vec<Stencil> = Vec::new();
+        for mut s in [self.clone(), self.reflect()] {
+            for _ in 0..4 {
+                let next = s.rotate();
+                if !variants.contains(&s) {
+                    variants.push(s);
+                }
+                s = next;
+            }
+        }
+        variants
+    }
+
+    /// The letters of the stencil and their offsets from its top left corner.
+    pub fn letters(&self) -> impl Iterator<Item = (usize, usize, char)> + '_ {
+        self.cells.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, cell)| cell.map(|ch| (x, y, ch)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let s = Stencil::parse("AB\n.\nC.D\n").unwrap();
+        assert_eq!((s.width, s.height), (3, 3));
+        assert_eq!(s.cells[0], vec![Some('A'), Some('B'), None]);
+        assert_eq!(s.cells[1], vec![None, None, None]);
+        assert!(Stencil::parse("..\n.").is_err());
+        assert!(Stencil::parse("").is_err());
+    }
+
+    #[test]
+    fn test_transforms() {
+        let s = Stencil::parse("AB\nC.").unwrap();
+        assert_eq!(s.rotate(), Stencil::parse("CA\n.B").unwrap());
+        assert_eq!(s.reflect(), Stencil::parse("BA\n.C").unwrap());
+        assert_eq!(s.rotate().rotate().rotate().rotate(), s);
+    }
+
+    #[test]
+    fn test_variants() {
+        assert_eq!(Stencil::parse(XMAS).unwrap().variants().len(), 4);
+        assert_eq!(Stencil::parse(".A.\nAAA\n.A.").unwrap().variants().len(), 1);
+        assert_eq!(Stencil::parse("XMAS").unwrap().variants().len(), 4);
+        assert_eq!(Stencil::parse("AB\nC.").unwrap().variants().len(), 8);
+    }
+}
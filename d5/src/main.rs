@@ -1,61 +1,147 @@
-use std::collections::HashMap;
-use std::env;
+use clap::Parser as _;
+use rules::Rules;
+use std::error::Error;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-fn main() {
-    let path = env::args().nth(1).unwrap();
-    run(&path);
+mod rules;
+
+#[derive(clap::Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Print each update and whether it needed fixing
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Answer whether the rules force page A to come before page B
+    #[arg(long, num_args = 2, value_names = ["A", "B"])]
+    before: Option<Vec<u32>>,
+
+    /// List the pages the rules force to come before and after a page
+    #[arg(long, value_name = "PAGE")]
+    around: Option<u32>,
+
+    /// Count the orders of each update allowed by the rules
+    #[arg(short, long)]
+    count: bool,
+
+    /// Print the rules as a Graphviz graph, without rules implied by others
+    #[arg(long)]
+    dot: bool,
+
+    /// Only consider the rules between the pages of the Nth update, counting from 1
+    #[arg(short, long, value_name = "N")]
+    update: Option<usize>,
 }
 
-fn run(path: &str) {
-    let mut parser = Parser::from_path(path);
-    let mut depmap = HashMap::<u16, u128>::new();
-    let mut sum = 0;
-
-    for dep in parser.deps() {
-        depmap
-            .entry(dep.before)
-            .and_modify(|m| *m |= 1 << dep.after)
-            .or_insert(1 << dep.after);
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if cli.before.is_some() || cli.around.is_some() || cli.count || cli.dot {
+        query(&cli)
+    } else {
+        run(&cli.file, cli.verbose);
+        Ok(())
     }
+}
 
-    for mut updates in parser.updates() {
-        if let Some((a, b)) = order_violation(&updates, &depmap) {
-            updates.swap(a, b);
-            while let Some((a, b)) = order_violation(&updates, &depmap) {
-                updates.swap(a, b);
-            }
-            println!("{:?} => Fixed!", updates);
-            sum += updates[updates.len() / 2];
-        } else {
-            println!("{:?} => Pristine!", updates);
+fn query(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let mut parser = Parser::from_path(&cli.file);
+    let mut rules = Rules::new(parser.deps());
+    let updates: Vec<Vec<u32>> = parser.updates().collect();
+    if let Some(n) = cli.update {
+        let update = n
+            .checked_sub(1)
+            .and_then(|i| updates.get(i))
+            .ok_or_else(|| format!("no update {}, they are numbered 1 to {}", n, updates.len()))?;
+        let mut queried = cli.before.iter().flatten().chain(&cli.around);
+        if let Some(page) = queried.find(|page| !update.contains(page)) {
+            return Err(format!("page {} is not in update {}", page, n).into());
         }
+        rules = rules.restrict(update);
     }
 
-    println!("{}", sum);
-}
+    if cli.dot || cli.before.is_some() || cli.around.is_some() {
+        let closure = rules.closure().map_err(|cycle| {
+            let deps: Vec<String> = cycle
+                .iter()
+                .map(|dep| format!("line {}: {}|{}", dep.line, dep.before, dep.after))
+                .collect();
+            format!(
+                "the rules form a cycle ({}), use --update to query the pages of one update",
+                deps.join(", ")
+            )
+        })?;
+        if cli.dot {
+            print!("{}", closure.dot());
+        }
+        if let Some(pages) = &cli.before {
+            let (a, b) = (pages[0], pages[1]);
+            let answer = if closure.before(a, b) { "yes" } else { "no" };
+            println!("Must {} come before {}? {}", a, b, answer);
+        }
+        if let Some(page) = cli.around {
+            println!("Before {}: {:?}", page, closure.predecessors(page));
+            println!("After {}: {:?}", page, closure.successors(page));
+        }
+    }
 
-fn order_violation(updates: &Vec<u16>, depmap: &HashMap<u16, u128>) -> Option<(usize, usize)> {
-    let mut pages_bitmap: u128 = 0;
+    if cli.count {
+        let updates = match cli.update {
+            Some(n) => &updates[n - 1..n],
+            None => &updates[..],
+        };
+        for update in updates {
+            match rules.orderings(update) {
+                Some(count) => println!("{:?} => {} orderings", update, count),
+                None => println!("{:?} => too many orderings to count", update),
+            }
+        }
+    }
+    Ok(())
+}
 
-    for (index, page) in updates.iter().enumerate() {
-        let conflicts = *depmap.get(page).unwrap_or(&0) & pages_bitmap;
-        if conflicts != 0 {
-            let other_page = conflicts.trailing_zeros() as u16;
-            let other_index = updates.iter().position(|x| *x == other_page).unwrap();
-            return Some((index, other_index));
+fn run(path: &str, verbose: bool) {
+    let mut parser = Parser::from_path(path);
+    let rules = Rules::new(parser.deps());
+    let mut pristine = 0;
+    let mut fixed = 0;
+
+    for update in parser.updates() {
+        if rules.is_ordered(&update) {
+            if verbose {
+                println!("{:?} => Pristine!", update);
+            }
+            pristine += update[update.len() / 2];
+        } else if let Some(sorted) = rules.sort(&update) {
+            if verbose {
+                println!("{:?} => Fixed!", sorted);
+            }
+            fixed += sorted[sorted.len() / 2];
         } else {
-            pages_bitmap |= 1 << page;
+            println!("{:?} => Rules form a cycle:", update);
+            for dep in rules.shortest_cycle(&update).unwrap_or_default() {
+                println!("  line {}: {}|{}", dep.line, dep.before, dep.after);
+            }
+            continue;
+        }
+
+        if let Some(middles) = rules.middle_pages(&update)
+            && middles.len() > 1
+        {
+            println!("{:?} => Middle page could be any of {:?}", update, middles);
         }
     }
 
-    None
+    println!("Pristine: {}", pristine);
+    println!("Fixed: {}", fixed);
 }
 
 struct Parser {
     lines: Box<dyn Iterator<Item = String>>,
+    line: usize,
 }
 
 impl Parser {
@@ -65,22 +151,30 @@ impl Parser {
         let lines = reader.lines().map(|x| x.unwrap());
         Parser {
             lines: Box::new(lines.into_iter()),
+            line: 0,
         }
     }
 
-    fn deps(&mut self) -> DepsIter {
+    fn deps(&mut self) -> DepsIter<'_> {
         DepsIter { parser: self }
     }
 
-    fn updates(&mut self) -> UpdatesIter {
+    fn updates(&mut self) -> UpdatesIter<'_> {
         UpdatesIter { parser: self }
     }
+
+    fn next_line(&mut self) -> Option<String> {
+        self.line += 1;
+        self.lines.next()
+    }
 }
 
-#[derive(Debug)]
+/// The rule `before|after`, from line `line` of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Dep {
-    before: u16,
-    after: u16,
+    before: u32,
+    after: u32,
+    line: usize,
 }
 
 struct DepsIter<'a> {
@@ -91,14 +185,18 @@ impl<'a> Iterator for DepsIter<'a> {
     type Item = Dep;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.parser.lines.next() {
+        match self.parser.next_line() {
             None => None,
-            Some(line) if line.len() == 0 => None,
+            Some(line) if line.is_empty() => None,
             Some(line) => {
                 let mut split = line.split('|');
-                let before = split.next().unwrap().parse::<u16>().unwrap();
-                let after = split.next().unwrap().parse::<u16>().unwrap();
-                Some(Dep { before, after })
+                let before = split.next().unwrap().parse::<u32>().unwrap();
+                let after = split.next().unwrap().parse::<u32>().unwrap();
+                Some(Dep {
+                    before,
+                    after,
+                    line: self.parser.line,
+                })
             }
         }
     }
@@ -109,13 +207,13 @@ struct UpdatesIter<'a> {
 }
 
 impl<'a> Iterator for UpdatesIter<'a> {
-    type Item = Vec<u16>;
+    type Item = Vec<u32>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.parser.lines.next() {
+        match self.parser.next_line() {
             None => None,
             Some(line) => {
-                let v: Vec<u16> = line.split(',').map(|x| x.parse::<u16>().unwrap()).collect();
+                let v: Vec<u32> = line.split(',').map(|x| x.parse::<u32>().unwrap()).collect();
                 Some(v)
             }
         }
@@ -0,0 +1,455 @@
+use crate::Dep;
+use fixedbitset::FixedBitSet;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
+
+/// The page ordering rules as a graph over the pages they mention.
+pub struct Rules {
+    ids: HashMap<u32, usize>,
+    pages: Vec<u32>,
+    /// `after[id]` holds the pages a rule says must come after page `id`.
+    after: Vec<FixedBitSet>,
+    /// The input line of the first rule between each pair of pages.
+    lines: HashMap<(usize, usize), usize>,
+}
+
+impl Rules {
+    pub fn new(deps: impl IntoIterator<Item = Dep>) -> Self {
+        let mut rules = Rules {
+            ids: HashMap::new(),
+            pages: Vec::new(),
+            after: Vec::new(),
+            lines: HashMap::new(),
+        };
+        for dep in deps {
+            let edge = (rules.intern(dep.before), rules.intern(dep.after));
+            rules.lines.entry(edge).or_insert(dep.line);
+        }
+
+        let n = rules.pages.len();
+        rules.after = vec![FixedBitSet::with_capacity(n); n];
+        for &(before, after) in rules.lines.keys() {
+            rules.after[before].insert(after);
+        }
+        rules
+    }
+
+    fn intern(&mut self, page: u32) -> usize {
+        *self.ids.entry(page).or_insert_with(|| {
+            self.pages.push(page);
+            self.pages.len() - 1
+        })
+    }
+
+    /// Whether a rule says `a` must come before `b`.
+    pub fn before(&self, a: u32, b: u32) -> bool {
+        match (self.ids.get(&a), self.ids.get(&b)) {
+            (Some(&a), Some(&b)) => self.after[a].contains(b),
+            _ => false,
+        }
+    }
+
+    /// Whether `update` breaks none of the rules.
+    pub fn is_ordered(&self, update: &[u32]) -> bool {
+        let mut seen = FixedBitSet::with_capacity(self.pages.len());
+        for page in update {
+            if let Some(&id) = self.ids.get(page) {
+                if !self.after[id].is_disjoint(&seen) {
+                    return false;
+                }
+                seen.insert(id);
+            }
+        }
+        true
+    }
+
+    /// The rule saying `a` must come before `b`, if any.
+    fn rule(&self, a: u32, b: u32) -> Option<Dep> {
+        let edge = (*self.ids.get(&a)?, *self.ids.get(&b)?);
+        self.lines.get(&edge).map(|&line| Dep {
+            before: a,
+            after: b,
+            line,
+        })
+    }
+
+    /// The rules between the pages of `update`: `succ[i]` lists the positions whose pages
+    /// must come after the page at position `i`.
+    fn local_graph(&self, update: &[u32]) -> Vec<Vec<usize>> {
+        update
+            .iter()
+            .map(|&a| {
+                (0..update.len())
+                    .filter(|&j| self.before(a, update[j]))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Reorders `update` to follow the rules between its pages, keeping pages in their
+    /// original order wherever the rules allow. Returns `None` if those rules form a cycle.
+    pub fn sort(&self, update: &[u32]) -> Option<Vec<u32>> {
+        let order = topo_order(&self.local_graph(update))?;
+        Some(order.into_iter().map(|i| update[i]).collect())
+    }
+
+    /// The shortest cycle among the rules between the pages of `update`, if any.
+    pub fn shortest_cycle(&self, update: &[u32]) -> Option<Vec<Dep>> {
+        let succ = self.local_graph(update);
+        let n = update.len();
+        let mut best: Option<Vec<usize>> = None;
+
+        for start in 0..n {
+            // Breadth first search for the shortest path back to `start`:
+            let mut parent: Vec<Option<usize>> = vec![None; n];
+            let mut queue = VecDeque::from([start]);
+            let mut end = None;
+            'search: while let Some(i) = queue.pop_front() {
+                for &j in &succ[i] {
+                    if j == start {
+                        end = Some(i);
+                        break 'search;
+                    }
+                    if parent[j].is_none() && j != start {
+                        parent[j] = Some(i);
+                        queue.push_back(j);
+                    }
+                }
+            }
+
+            let Some(mut i) = end else { continue };
+            let mut cycle = vec![i];
+            while i != start {
+                i = parent[i].unwrap();
+                cycle.push(i);
+            }
+            if best.as_ref().is_none_or(|b| cycle.len() < b.len()) {
+                best = Some(cycle);
+            }
+        }
+
+        let mut cycle = best?;
+        cycle.reverse();
+        let deps = (0..cycle.len())
+            .map(|k| {
+                let (a, b) = (cycle[k], cycle[(k + 1) % cycle.len()]);
+                self.rule(update[a], update[b]).unwrap()
+            })
+            .collect();
+        Some(deps)
+    }
+
+    /// Every page that is the middle page of some order of `update` allowed by the rules.
+    /// Returns `None` if the rules between its pages form a cycle.
+    ///
+    /// A page can land at any position from the number of pages that must precede it up
+    /// to the number that may precede it.
+    pub fn middle_pages(&self, update: &[u32]) -> Option<Vec<u32>> {
+        let succ = self.local_graph(update);
+        let order = topo_order(&succ)?;
+        let n = update.len();
+        let mid = n / 2;
+
+        // Everything each position must precede, filled in reverse topological order:
+        let mut desc = vec![FixedBitSet::with_capacity(n); n];
+        for &i in order.iter().rev() {
+            for &j in &succ[i] {
+                desc[i].insert(j);
+                let below = desc[j].clone();
+                desc[i].union_with(&below);
+            }
+        }
+        let mut anc_count = vec![0; n];
+        for d in &desc {
+            for j in d.ones() {
+                anc_count[j] += 1;
+            }
+        }
+
+        let pages = (0..n)
+            .filter(|&i| anc_count[i] <= mid && mid < n - desc[i].count_ones(..))
+            .map(|i| update[i])
+            .collect();
+        Some(pages)
+    }
+
+    /// The rules between `pages` only.
+    pub fn restrict(&self, pages: &[u32]) -> Rules {
+        let mut deps: Vec<Dep> = self
+            .lines
+            .iter()
+            .map(|(&(a, b), &line)| Dep {
+                before: self.pages[a],
+                after: self.pages[b],
+                line,
+            })
+            .filter(|dep| pages.contains(&dep.before) && pages.contains(&dep.after))
+            .collect();
+        deps.sort_by_key(|dep| dep.line);
+        Rules::new(deps)
+    }
+
+    /// The shortest cycle among all the rules, if any.
+    pub fn cycle(&self) -> Option<Vec<Dep>> {
+        match topo_order(&self.local_graph(&self.pages)) {
+            Some(_) => None,
+            None => self.shortest_cycle(&self.pages),
+        }
+    }
+
+    /// Every page reachable from each page by following rules, directly or through other
+    /// pages. If the rules form a cycle every page on it reaches every other, so rather than
+    /// answer nonsense this returns the cycle as an error.
+    pub fn closure(&self) -> Result<Closure<'_>, Vec<Dep>> {
+        if let Some(cycle) = self.cycle() {
+            return Err(cycle);
+        }
+        let mut reach = self.after.clone();
+        for k in 0..self.pages.len() {
+            let via = reach[k].clone();
+            for r in reach.iter_mut() {
+                if r.contains(k) {
+                    r.union_with(&via);
+                }
+            }
+        }
+        Ok(Closure { rules: self, reach })
+    }
+
+    /// How many orders of `update` follow the rules. Returns `None` if the count overflows,
+    /// or if it would take more than `MAX_ORDERING_STATES` partial orders to work out, which
+    /// loosely constrained updates of a couple of dozen pages or more can.
+    pub fn orderings(&self, update: &[u32]) -> Option<u128> {
+        if update.len() > 64 {
+            return None;
+        }
+        // The positions that must be placed before each position:
+        let mut preds = vec![0u64; update.len()];
+        for (i, succ) in self.local_graph(update).iter().enumerate() {
+            for &j in succ {
+                preds[j] |= 1 << i;
+            }
+        }
+        let mut memo = HashMap::new();
+        count_orderings(&preds, 0, &mut memo)
+    }
+}
+
+/// The transitive closure of the rules.
+pub struct Closure<'r> {
+    rules: &'r Rules,
+    reach: Vec<FixedBitSet>,
+}
+
+impl Closure<'_> {
+    /// Whether the rules force `a` to come before `b`.
+    pub fn before(&self, a: u32, b: u32) -> bool {
+        match (self.rules.ids.get(&a), self.rules.ids.get(&b)) {
+            (Some(&a), Some(&b)) => self.reach[a].contains(b),
+            _ => false,
+        }
+    }
+
+    /// The pages the rules force to come before `page`, in ascending order.
+    pub fn predecessors(&self, page: u32) -> Vec<u32> {
+        let Some(&id) = self.rules.ids.get(&page) else {
+            return Vec::new();
+        };
+        let mut pages: Vec<u32> = (0..self.reach.len())
+            .filter(|&i| self.reach[i].contains(id))
+            .map(|i| self.rules.pages[i])
+            .collect();
+        pages.sort();
+        pages
+    }
+
+    /// The pages the rules force to come after `page`, in ascending order.
+    pub fn successors(&self, page: u32) -> Vec<u32> {
+        let Some(&id) = self.rules.ids.get(&page) else {
+            return Vec::new();
+        };
+        let mut pages: Vec<u32> = self.reach[id].ones().map(|i| self.rules.pages[i]).collect();
+        pages.sort();
+        pages
+    }
+
+    /// The rules as a Graphviz graph, leaving out rules implied by others.
+    pub fn dot(&self) -> String {
+        let mut edges: Vec<(u32, u32)> = Vec::new();
+        for (u, after) in self.rules.after.iter().enumerate() {
+            for v in after.ones() {
+                let implied = after.ones().any(|w| w != v && self.reach[w].contains(v));
+                if !implied {
+                    edges.push((self.rules.pages[u], self.rules.pages[v]));
+                }
+            }
+        }
+        edges.sort();
+
+        let mut out = String::from("digraph rules {\n");
+        for (before, after) in edges {
+            writeln!(out, "    {} -> {};", before, after).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// The most sets of placed pages `Rules::orderings` will count the orders from.
+pub const MAX_ORDERING_STATES: usize = 1 << 16;
+
+/// Counts the ways to finish an order once the positions in `placed` are placed.
+fn count_orderings(preds: &[u64], placed: u64, memo: &mut HashMap<u64, u128>) -> Option<u128> {
+    let n = preds.len();
+    if placed.count_ones() as usize == n {
+        return Some(1);
+    }
+    if let Some(&count) = memo.get(&placed) {
+        return Some(count);
+    }
+    if memo.len() >= MAX_ORDERING_STATES {
+        return None;
+    }
+
+    let mut count: u128 = 0;
+    for (j, &pred) in preds.iter().enumerate() {
+        if placed & (1 << j) == 0 && pred & !placed == 0 {
+            count = count.checked_add(count_orderings(preds, placed | 1 << j, memo)?)?;
+        }
+    }
+    memo.insert(placed, count);
+    Some(count)
+}
+
+/// Kahn's algorithm, taking the earliest ready position first.
+fn topo_order(succ: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let n = succ.len();
+    let mut indegree = vec![0; n];
+    for &j in succ.iter().flatten() {
+        indegree[j] += 1;
+    }
+
+    let mut ready: BTreeSet<usize> = (0..n).filter(|&i| indegree[i] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some(i) = ready.pop_first() {
+        order.push(i);
+        for &j in &succ[i] {
+            indegree[j] -= 1;
+            if indegree[j] == 0 {
+                ready.insert(j);
+            }
+        }
+    }
+    (order.len() == n).then_some(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(deps: &[(u32, u32)]) -> Rules {
+        Rules::new(deps.iter().enumerate().map(|(i, &(before, after))| Dep {
+            before,
+            after,
+            line: i + 1,
+        }))
+    }
+
+    #[test]
+    fn test_sort() {
+        let r = rules(&[(97, 75), (75, 47), (97, 47), (47, 13)]);
+        assert!(r.is_ordered(&[97, 75, 47, 13]));
+        assert!(!r.is_ordered(&[75, 97, 47]));
+        assert_eq!(r.sort(&[75, 97, 47]), Some(vec![97, 75, 47]));
+        assert_eq!(r.sort(&[13, 5, 47]), Some(vec![5, 47, 13]));
+    }
+
+    #[test]
+    fn test_large_pages() {
+        let r = rules(&[(1000, 200), (200, 4_000_000)]);
+        assert!(!r.is_ordered(&[4_000_000, 200, 1000]));
+        assert_eq!(
+            r.sort(&[4_000_000, 200, 1000]),
+            Some(vec![1000, 200, 4_000_000])
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let r = rules(&[(1, 2), (2, 3), (3, 1)]);
+        assert_eq!(r.sort(&[1, 2, 3]), None);
+        assert_eq!(r.sort(&[1, 3]), Some(vec![3, 1]));
+    }
+
+    #[test]
+    fn test_shortest_cycle() {
+        let r = rules(&[(1, 2), (2, 3), (3, 4), (4, 1), (3, 1), (5, 5)]);
+        let lines = |update: &[u32]| {
+            r.shortest_cycle(update)
+                .map(|c| c.iter().map(|d| d.line).collect::<Vec<_>>())
+        };
+        assert_eq!(lines(&[1, 2, 3, 4]), Some(vec![1, 2, 5]));
+        assert_eq!(lines(&[4, 2, 1]), None);
+        assert_eq!(lines(&[5, 1]), Some(vec![6]));
+        assert_eq!(r.shortest_cycle(&[2, 1, 3]).unwrap()[0].before, 2);
+    }
+
+    #[test]
+    fn test_middle_pages() {
+        let r = rules(&[(1, 2), (2, 3), (1, 3)]);
+        assert_eq!(r.middle_pages(&[3, 1, 2]), Some(vec![2]));
+        // 4 is unconstrained, so any page could end up in the middle:
+        assert_eq!(r.middle_pages(&[1, 2, 4]), Some(vec![1, 2, 4]));
+        assert_eq!(r.middle_pages(&[1, 2, 3, 4]), Some(vec![2, 3, 4]));
+        let r = rules(&[(1, 2), (2, 1)]);
+        assert_eq!(r.middle_pages(&[1, 2]), None);
+    }
+
+    #[test]
+    fn test_closure() {
+        let r = rules(&[(1, 2), (2, 3), (4, 3), (5, 6), (6, 5)]);
+        let cycle = r.closure().err().unwrap();
+        assert_eq!(cycle.iter().map(|d| d.line).collect::<Vec<_>>(), vec![4, 5]);
+
+        let r = r.restrict(&[1, 2, 3, 4, 5]);
+        assert!(r.cycle().is_none());
+        let c = r.closure().unwrap();
+        assert!(c.before(1, 3));
+        assert!(!c.before(3, 1));
+        assert!(!c.before(1, 4));
+        assert!(!c.before(1, 1));
+        assert!(!c.before(1, 99));
+        assert_eq!(c.predecessors(3), vec![1, 2, 4]);
+        assert_eq!(c.successors(1), vec![2, 3]);
+        assert_eq!(c.successors(99), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_orderings() {
+        let r = rules(&[(1, 2), (2, 3), (1, 3), (4, 5), (5, 4)]);
+        assert_eq!(r.orderings(&[1, 2, 3]), Some(1));
+        assert_eq!(r.orderings(&[3, 1, 7]), Some(3));
+        assert_eq!(r.orderings(&[6, 7, 8]), Some(6));
+        assert_eq!(r.orderings(&[4, 5]), Some(0));
+        assert_eq!(r.orderings(&[]), Some(1));
+
+        // A chain is quick to count however long, but unconstrained pages aren't:
+        let chain: Vec<(u32, u32)> = (0..63).map(|i| (i, i + 1)).collect();
+        let r = rules(&chain);
+        assert_eq!(r.orderings(&(0..64).rev().collect::<Vec<_>>()), Some(1));
+        assert_eq!(r.orderings(&(100..130).collect::<Vec<_>>()), None);
+        assert_eq!(
+            r.orderings(&(100..112).collect::<Vec<_>>()),
+            Some(479001600)
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let r = rules(&[(1, 2), (2, 3), (1, 3), (4, 5), (4, 6), (5, 6)]);
+        assert_eq!(
+            r.closure().unwrap().dot(),
+            "digraph rules {\n    1 -> 2;\n    2 -> 3;\n    4 -> 5;\n    5 -> 6;\n}\n"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{Direction, Map, Position};
+use std::thread;
+
+pub type Cell = (usize, usize);
+
+/// A guard at `cell` facing `dir`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct State {
+    pub cell: Cell,
+    pub dir: Direction,
+}
+
+/// The map as a dense grid, with the sorted obstacles of every row and column so a guard can
+/// move straight to the next obstacle instead of cell by cell.
+pub struct Grid {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    /// `rows[y]` holds the x of every obstacle in row y, ascending.
+    rows: Vec<Vec<usize>>,
+    /// `cols[x]` holds the y of every obstacle in column x, ascending.
+    cols: Vec<Vec<usize>>,
+}
+
+impl Grid {
+    pub fn new(map: &Map) -> Self {
+        let width = (map.x_max + 1) as usize;
+        let height = (map.y_max + 1) as usize;
+        let mut blocked = vec![false; width * height];
+        let mut rows = vec![Vec::new(); height];
+        let mut cols = vec![Vec::new(); width];
+        for loc in &map.blocks {
+            let (x, y) = (loc.x as usize, loc.y as usize);
+            blocked[y * width + x] = true;
+            rows[y].push(x);
+            cols[x].push(y);
+        }
+        rows.iter_mut()
+            .chain(cols.iter_mut())
+            .for_each(|v| v.sort());
+        Grid {
+            width,
+            height,
+            blocked,
+            rows,
+            cols,
+        }
+    }
+
+    fn index(&self, (x, y): Cell) -> usize {
+        y * self.width + x
+    }
+
+    /// The neighbouring cell in `dir`, if it is on the map.
+    fn step(&self, (x, y): Cell, dir: Direction) -> Option<Cell> {
+        match dir {
+            Direction::N => y.checked_sub(1).map(|y| (x, y)),
+            Direction::E => (x + 1 < self.width).then_some((x + 1, y)),
+            Direction::S => (y + 1 < self.height).then_some((x, y + 1)),
+            Direction::W => x.checked_sub(1).map(|x| (x, y)),
+        }
+    }
+
+    /// Where a guard moving from `state` stops in front of an obstacle, treating the cells in
+    /// `extra` as obstacles too. Returns `None` if the guard walks off the map.
+    fn jump(&self, state: State, extra: &[Cell]) -> Option<Cell> {
+        let (x, y) = state.cell;
+        let ahead = extra.iter().filter(|&&(ex, ey)| match state.dir {
+            Direction::N => ex == x && ey < y,
+            Direction::S => ex == x && ey > y,
+            Direction::E => ey == y && ex > x,
+            Direction::W => ey == y && ex < x,
+        });
+        match state.dir {
+            Direction::N => {
+                let col = &self.cols[x];
+                let found = col[..col.partition_point(|&oy| oy < y)].last().copied();
+                let oy = found.max(ahead.map(|&(_, ey)| ey).max())?;
+                Some((x, oy + 1))
+            }
+            Direction::S => {
+                let col = &self.cols[x];
+                let found = col.get(col.partition_point(|&oy| oy <= y)).copied();
+                let oy = nearest(found, ahead.map(|&(_, ey)| ey).min())?;
+                Some((x, oy - 1))
+            }
+            Direction::W => {
+                let row = &self.rows[y];
+                let found = row[..row.partition_point(|&ox| ox < x)].last().copied();
+                let ox = found.max(ahead.map(|&(ex, _)| ex).max())?;
+                Some((ox + 1, y))
+            }
+            Direction::E => {
+                let row = &self.rows[y];
+                let found = row.get(row.partition_point(|&ox| ox <= x)).copied();
+                let ox = nearest(found, ahead.map(|&(ex, _)| ex).min())?;
+                Some((ox - 1, y))
+            }
+        }
+    }
+
+    /// Whether a guard starting from `state` ends up in a loop once `extra` is blocked.
+    fn loops(&self, mut state: State, extra: &[Cell], seen: &mut Seen) -> bool {
+        seen.clear();
+        loop {
+            let Some(cell) = self.jump(state, extra) else {
+                return false;
+            };
+            // Turning in the same place and direction twice means the guard is going round:
+            if !seen.insert(self.index(cell), state.dir) {
+                return true;
+            }
+            state = State {
+                cell,
+                dir: state.dir.right(),
+            };
+        }
+    }
+
+    /// Walks the guard's route cell by cell with the cells in `extra` blocked too. Returns
+    /// every cell it reaches after leaving the start, in the order first reached, with the
+    /// guard's state just before stepping onto it, and whether the guard ends up in a loop.
+    pub fn route(&self, start: State, extra: &[Cell]) -> (Vec<(Cell, State)>, bool) {
+        let mut visited = vec![false; self.blocked.len()];
+        let mut seen = Seen::new(self.blocked.len());
+        visited[self.index(start.cell)] = true;
+
+        let mut route = Vec::new();
+        let mut state = start;
+        while seen.insert(self.index(state.cell), state.dir) {
+            let Some(next) = self.step(state.cell, state.dir) else {
+                return (route, false);
+            };
+            let idx = self.index(next);
+            if self.blocked[idx] || extra.contains(&next) {
+                state.dir = state.dir.right();
+            } else {
+                if !visited[idx] {
+                    visited[idx] = true;
+                    route.push((next, state));
+                }
+                state.cell = next;
+            }
+        }
+        (route, true)
+    }
+
+    /// The number of distinct cells the guard visits, including the start.
+    pub fn patrol(&self, start: &Position) -> usize {
+        self.route(state(start), &[]).0.len() + 1
+    }
+
+    /// The number of cells where a single new obstacle traps the guard in a loop.
+    ///
+    /// Only cells on the guard's route matter, and the route up to the first time the
+    /// guard would reach the new obstacle is unchanged, so each candidate is simulated from
+    /// there. Candidates are split across threads.
+    pub fn loop_makers(&self, start: &Position) -> usize {
+        let (route, _) = self.route(state(start), &[]);
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = route.len().div_ceil(threads).max(1);
+
+        thread::scope(|s| {
+            let handles: Vec<_> = route
+                .chunks(chunk)
+                .map(|candidates| {
+                    s.spawn(move || {
+                        let mut seen = Seen::new(self.blocked.len());
+                        candidates
+                            .iter()
+                            .filter(|&&(cell, before)| self.loops(before, &[cell], &mut seen))
+                            .count()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        })
+    }
+
+    /// Where a guard goes from every state with the cells in `extra` blocked too.
+    pub fn tails(&self, extra: &[Cell]) -> Tails {
+        let mut blocked = self.blocked.clone();
+        for &cell in extra {
+            blocked[self.index(cell)] = true;
+        }
+
+        // The state each state leads to, one move or turn later, and the states leading to
+        // each, grouped by the state they lead to:
+        let states = self.blocked.len() * 4;
+        let mut next = vec![None; states];
+        for (idx, _) in blocked.iter().enumerate().filter(|(_, b)| !**b) {
+            let cell = (idx % self.width, idx / self.width);
+            for dir in DIRECTIONS {
+                next[idx * 4 + dir_index(dir)] = self.step(cell, dir).map(|ahead| {
+                    let i = self.index(ahead);
+                    match blocked[i] {
+                        true => idx * 4 + dir_index(dir.right()),
+                        false => i * 4 + dir_index(dir),
+                    }
+                });
+            }
+        }
+        let mut start = vec![0; states + 1];
+        for &n in next.iter().flatten() {
+            start[n + 1] += 1;
+        }
+        for i in 0..states {
+            start[i + 1] += start[i];
+        }
+        let mut fill = start.clone();
+        let mut from = vec![0; start[states]];
+        for (s, n) in next.iter().enumerate() {
+            if let Some(n) = *n {
+                from[fill[n]] = s;
+                fill[n] += 1;
+            }
+        }
+
+        // Walk back from the states that leave the map; states never reached end in a loop:
+        let mut tails = Tails {
+            steps: vec![u32::MAX; states],
+            enter: vec![0; states],
+            leave: vec![0; states],
+        };
+        let mut clock = 0;
+        for exit in (0..states).filter(|&s| next[s].is_none() && !blocked[s / 4]) {
+            tails.steps[exit] = 0;
+            tails.enter[exit] = clock;
+            clock += 1;
+            let mut stack = vec![(exit, start[exit])];
+            while let Some((s, i)) = stack.last_mut() {
+                let s = *s;
+                if *i == start[s + 1] {
+                    tails.leave[s] = clock;
+                    stack.pop();
+                    continue;
+                }
+                let prev = from[*i];
+                *i += 1;
+                // Only moving, not turning, enters a cell:
+                let moved = prev / 4 != s / 4;
+                tails.steps[prev] = tails.steps[s] + moved as u32;
+                tails.enter[prev] = clock;
+                clock += 1;
+                stack.push((prev, start[prev]));
+            }
+        }
+        tails
+    }
+
+    fn state_index(&self, state: State) -> usize {
+        self.index(state.cell) * 4 + dir_index(state.dir)
+    }
+}
+
+/// How a guard goes on from every state, for bounding routes without walking them.
+pub struct Tails {
+    /// How many cells a guard in each state enters before leaving the map, or `u32::MAX` if
+    /// it ends up in a loop.
+    steps: Vec<u32>,
+    /// When each state was entered and left in a depth first search back from the states
+    /// that leave the map: a guard in state `a` passes through state `b` if `b`'s interval
+    /// holds `a`'s.
+    enter: Vec<u32>,
+    leave: Vec<u32>,
+}
+
+impl Tails {
+    /// How many cells a guard in state `from` enters before leaving the map, if it leaves
+    /// without stepping onto `avoid`.
+    pub fn exit_steps(&self, grid: &Grid, from: State, avoid: Cell) -> Option<usize> {
+        let a = grid.state_index(from);
+        if self.steps[a] == u32::MAX {
+            return None;
+        }
+        let passes = DIRECTIONS.iter().any(|&dir| {
+            let b = grid.state_index(State { cell: avoid, dir });
+            self.steps[b] != u32::MAX
+                && self.enter[b] <= self.enter[a]
+                && self.leave[a] <= self.leave[b]
+        });
+        (!passes).then_some(self.steps[a] as usize)
+    }
+}
+
+const DIRECTIONS: [Direction; 4] = [Direction::N, Direction::E, Direction::S, Direction::W];
+
+fn dir_index(dir: Direction) -> usize {
+    (dir as u8).trailing_zeros() as usize
+}
+
+pub fn state(pos: &Position) -> State {
+    State {
+        cell: (pos.loc.x as usize, pos.loc.y as usize),
+        dir: pos.dir,
+    }
+}
+
+/// The closer of two obstacles ahead in an ascending direction.
+fn nearest(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// The directions a guard has faced at each cell, cheap to reset between simulations.
+struct Seen {
+    dirs: Vec<u8>,
+    touched: Vec<usize>,
+}
+
+impl Seen {
+    fn new(cells: usize) -> Self {
+        Seen {
+            dirs: vec![0; cells],
+            touched: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        for idx in self.touched.drain(..) {
+            self.dirs[idx] = 0;
+        }
+    }
+
+    /// Records facing `dir` at cell `idx`, returning `false` if it had already been seen.
+    fn insert(&mut self, idx: usize, dir: Direction) -> bool {
+        let bits = &mut self.dirs[idx];
+        if *bits & dir as u8 != 0 {
+            return false;
+        }
+        if *bits == 0 {
+            self.touched.push(idx);
+        }
+        *bits |= dir as u8;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loop_makers, parse_map, patrol};
+
+    #[test]
+    fn test_matches_reference() {
+        let (map, guards) = parse_map("sample.txt");
+        let start = guards[0].clone();
+        let grid = Grid::new(&map);
+        assert_eq!(grid.patrol(&start), patrol(&start, &map).map.len());
+        assert_eq!(grid.loop_makers(&start), loop_makers(&start, &map));
+        assert_eq!(grid.loop_makers(&start), 6);
+    }
+
+    #[test]
+    fn test_tails() {
+        let (map, guards) = parse_map("sample.txt");
+        let grid = Grid::new(&map);
+        let start = state(&guards[0]);
+        let (route, _) = grid.route(start, &[]);
+
+        // The guard enters 44 cells, crossing 4 of them twice, unless its way is barred:
+        let tails = grid.tails(&[]);
+        assert_eq!(tails.exit_steps(&grid, start, (0, 0)), Some(44));
+        assert_eq!(tails.exit_steps(&grid, start, route[30].0), None);
+
+        // With the first trap blocked the guard goes round forever:
+        let tails = grid.tails(&[(3, 6)]);
+        assert_eq!(tails.exit_steps(&grid, start, (0, 0)), None);
+    }
+}
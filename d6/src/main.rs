@@ -1,33 +1,187 @@
+use aoc::cache::{Cache, CacheArgs};
+use clap::Parser;
+use jump::Grid;
+use patrol::{Collision, Guard, Outcome, Policy};
 use std::collections::{HashMap, HashSet};
-use std::env;
+use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::Chars;
 
-fn main() {
-    let path = env::args().nth(1).unwrap();
-    let (map, start) = parse_map(&path);
+mod jump;
+mod optimize;
+mod patrol;
+mod report;
+
+const SOLVER_VERSION: u32 = 1;
+
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Simulate cell by cell with the reference simulator
+    #[arg(long)]
+    naive: bool,
+
+    /// Run every guard on the map at once and report what each covers
+    #[arg(short, long)]
+    simulate: bool,
+
+    /// How guards turn at obstacles: right, left, reverse, or a sequence such as RRL
+    #[arg(short, long, default_value = "right", requires = "simulate")]
+    policy: Policy,
+
+    /// What a guard does when it walks into another guard
+    #[arg(short, long, value_enum, default_value_t = Collision::Wait, requires = "simulate")]
+    collide: Collision,
+
+    /// Describe the loop each loop-making obstruction causes and render the candidates
+    #[arg(short, long)]
+    report: bool,
+
+    /// Search placements of up to K extra obstacles for the longest patrol, and for loops
+    #[arg(short, long, value_name = "K")]
+    optimize: Option<usize>,
+
+    #[command(flatten)]
+    cache: CacheArgs,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let (map, guards) = parse_map(&cli.file);
+    if cli.simulate {
+        simulate(&map, guards, &cli);
+        return Ok(());
+    }
+    let [start] = <[Position; 1]>::try_from(guards).map_err(|guards| match guards.len() {
+        0 => "no guard on the map".to_owned(),
+        n => format!("found {} guards, use --simulate to run them together", n),
+    })?;
+
+    if cli.report {
+        report::report(&map, &start);
+        return Ok(());
+    }
+    if let Some(k) = cli.optimize {
+        optimize(&map, &start, k);
+        return Ok(());
+    }
+
+    let cache = Cache::open("d6", SOLVER_VERSION, &cli.file, &cli.cache)?;
+
+    let params = if cli.naive { "naive" } else { "" };
+    let grid = Grid::new(&map);
+
+    let unique: usize = cache.get_or_insert_with(1, params, || match cli.naive {
+        true => patrol(&start, &map).map.len(),
+        false => grid.patrol(&start),
+    });
+    println!("Unique Locations: {}", unique);
+
+    let loop_makers: usize = cache.get_or_insert_with(2, params, || match cli.naive {
+        true => loop_makers(&start, &map),
+        false => grid.loop_makers(&start),
+    });
+    println!("Loop Makers: {:?}", loop_makers);
+    Ok(())
+}
+
+fn simulate(map: &Map, starts: Vec<Position>, cli: &Cli) {
+    if starts.is_empty() {
+        println!("No guards on the map");
+        return;
+    }
+    let guards = starts
+        .into_iter()
+        .map(|pos| Guard::with_policy(pos, cli.policy.clone()))
+        .collect();
+    let report = patrol::simulate(map, guards, cli.collide);
+
+    let mut covered = HashSet::new();
+    for (i, guard) in report.guards.iter().enumerate() {
+        let status = match guard.exited {
+            Some(tick) => format!("left after {} steps", tick),
+            None => "still on the map".to_owned(),
+        };
+        println!(
+            "Guard {} at ({}, {}) facing {}: {} cells, {}",
+            i + 1,
+            guard.start.loc.x,
+            guard.start.loc.y,
+            guard.start.dir.symbol(),
+            guard.visited.len(),
+            status
+        );
+        covered.extend(guard.visited.iter().copied());
+    }
+    println!("Covered: {} cells", covered.len());
+    match report.outcome {
+        Outcome::Exited(tick) => println!("All guards left after {} steps", tick),
+        Outcome::Loop { start, length } => println!(
+            "Loop: the guards repeat every {} steps from step {}",
+            length, start
+        ),
+    }
+}
+
+fn optimize(map: &Map, start: &Position, k: usize) {
+    let cells = |blocks: &[Location]| {
+        let cells: Vec<String> = blocks
+            .iter()
+            .map(|l| format!("({}, {})", l.x, l.y))
+            .collect();
+        cells.join(" ")
+    };
+
+    let search = optimize::optimize(map, start, k);
+    let (blocks, visited) = &search.best;
+    if blocks.is_empty() {
+        println!("Longest patrol: {} cells, no extra obstacles help", visited);
+    } else {
+        println!(
+            "Longest patrol: {} cells, with obstacles at {}",
+            visited,
+            cells(blocks)
+        );
+    }
+    match &search.trap {
+        Some(blocks) => println!("Loop: obstacles at {} trap the guard", cells(blocks)),
+        None => println!("No placement of up to {} obstacles traps the guard", k),
+    }
+    println!(
+        "Explored {} placements, pruned {}",
+        search.explored, search.pruned
+    );
+}
+
+fn patrol(start: &Position, map: &Map) -> PosHistory {
     let mut history = PosHistory::new();
-    run(start.clone(), &map, &mut history);
-    println!("Unique Locations: {}", history.map.len());
+    run(start.clone(), map, &mut history);
+    history
+}
 
+fn loop_makers(start: &Position, map: &Map) -> usize {
     let mut loop_makers = 0;
-    for pos in history.map.keys() {
+    for pos in patrol(start, map).map.keys() {
         if *pos == start.loc {
             continue;
         }
         if run(
             start.clone(),
-            &AltMap::new(&map, *pos),
+            &AltMap::new(map, *pos),
             &mut PosHistory::new(),
-        ) {
+        )
+        .is_some()
+        {
             loop_makers += 1;
         }
     }
-    println!("Loop Makers: {:?}", loop_makers);
+    loop_makers
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 enum Direction {
     N = 1,
     E = 2,
@@ -35,12 +189,52 @@ enum Direction {
     W = 8,
 }
 
+impl Direction {
+    fn right(self) -> Direction {
+        match self {
+            Direction::N => Direction::E,
+            Direction::E => Direction::S,
+            Direction::S => Direction::W,
+            Direction::W => Direction::N,
+        }
+    }
+
+    fn left(self) -> Direction {
+        self.right().reverse()
+    }
+
+    fn reverse(self) -> Direction {
+        self.right().right()
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Direction::N => '^',
+            Direction::E => '>',
+            Direction::S => 'v',
+            Direction::W => '<',
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 struct Location {
     x: isize,
     y: isize,
 }
 
+impl Location {
+    fn step(self, dir: Direction) -> Location {
+        let Location { x, y } = self;
+        match dir {
+            Direction::N => Location { x, y: y - 1 },
+            Direction::E => Location { x: x + 1, y },
+            Direction::S => Location { x, y: y + 1 },
+            Direction::W => Location { x: x - 1, y },
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 struct Position {
     loc: Location,
@@ -50,12 +244,15 @@ struct Position {
 #[derive(Clone)]
 struct PosHistory {
     map: HashMap<Location, u8>,
+    /// Every position in the order inserted.
+    trace: Vec<Position>,
 }
 
 impl PosHistory {
     fn new() -> Self {
         PosHistory {
             map: HashMap::<Location, u8>::new(),
+            trace: Vec::new(),
         }
     }
 
@@ -64,63 +261,26 @@ impl PosHistory {
             .entry(pos.loc)
             .and_modify(|s| *s |= pos.dir as u8)
             .or_insert(pos.dir as u8);
+        self.trace.push(pos.clone());
     }
 
     fn check(&self, pos: &Position) -> bool {
-        if let Some(v) = self.map.get(&pos.loc) {
-            if v & pos.dir as u8 != 0 {
-                return true;
-            }
+        if let Some(v) = self.map.get(&pos.loc)
+            && v & pos.dir as u8 != 0
+        {
+            return true;
         }
         false
     }
 }
 
-struct Guard {
-    pos: Position,
-}
-
-impl Guard {
-    fn new(pos: Position) -> Self {
-        Guard { pos }
-    }
-
-    fn next_step(&self) -> Location {
-        match self.pos.dir {
-            Direction::N => Location {
-                x: self.pos.loc.x,
-                y: self.pos.loc.y - 1,
-            },
-            Direction::E => Location {
-                x: self.pos.loc.x + 1,
-                y: self.pos.loc.y,
-            },
-            Direction::S => Location {
-                x: self.pos.loc.x,
-                y: self.pos.loc.y + 1,
-            },
-            Direction::W => Location {
-                x: self.pos.loc.x - 1,
-                y: self.pos.loc.y,
-            },
-        }
-    }
-
-    fn next_direction(&self) -> Direction {
-        match self.pos.dir {
-            Direction::N => Direction::E,
-            Direction::E => Direction::S,
-            Direction::S => Direction::W,
-            Direction::W => Direction::N,
-        }
-    }
-}
-
-fn run(start: Position, map: &dyn MapGetter, history: &mut PosHistory) -> bool {
+/// Walks the guard from `start` until it leaves the map or repeats a position, recording each
+/// position in `history`. Returns the repeated position, where the guard's loop begins.
+fn run(start: Position, map: &dyn MapGetter, history: &mut PosHistory) -> Option<Position> {
     let mut guard = Guard::new(start);
 
     if history.check(&guard.pos) {
-        return true;
+        return Some(guard.pos);
     }
 
     history.insert(&guard.pos);
@@ -128,19 +288,19 @@ fn run(start: Position, map: &dyn MapGetter, history: &mut PosHistory) -> bool {
         let next_loc = guard.next_step();
         match map.get(next_loc) {
             Some(MapElement::Block) => {
-                guard.pos.dir = guard.next_direction();
+                guard.turn();
             }
             Some(MapElement::Space) => {
                 guard.pos.loc = next_loc;
             }
             _ => {
-                return false;
+                return None;
             }
         }
 
         // Have we been here before?
-        if history.check(&guard.pos) == true {
-            return true;
+        if history.check(&guard.pos) {
+            return Some(guard.pos);
         }
         history.insert(&guard.pos);
     }
@@ -166,38 +326,41 @@ impl MapGetter for Map {
     fn get(&self, loc: Location) -> Option<MapElement> {
         if loc.x < 0 || loc.x > self.x_max || loc.y < 0 || loc.y > self.y_max {
             None
+        } else if self.blocks.contains(&loc) {
+            Some(MapElement::Block)
         } else {
-            if let Some(_) = self.blocks.get(&loc) {
-                Some(MapElement::Block)
-            } else {
-                Some(MapElement::Space)
-            }
+            Some(MapElement::Space)
         }
     }
 }
 
+/// A map with extra obstacles.
 struct AltMap<'a> {
     map: &'a Map,
-    loc: Location,
+    blocks: HashSet<Location>,
 }
 
 impl<'a> AltMap<'a> {
     fn new(map: &'a Map, loc: Location) -> Self {
-        AltMap { map, loc }
+        AltMap::with_blocks(map, HashSet::from([loc]))
+    }
+
+    fn with_blocks(map: &'a Map, blocks: HashSet<Location>) -> Self {
+        AltMap { map, blocks }
     }
 }
 
 impl MapGetter for AltMap<'_> {
     fn get(&self, loc: Location) -> Option<MapElement> {
         match self.map.get(loc) {
-            Some(MapElement::Space) if loc == self.loc => Some(MapElement::Block),
+            Some(MapElement::Space) if self.blocks.contains(&loc) => Some(MapElement::Block),
             x => x,
         }
     }
 }
 
-fn parse_map(path: &str) -> (Map, Position) {
-    let mut start: Option<Position> = None;
+fn parse_map(path: &str) -> (Map, Vec<Position>) {
+    let mut guards = Vec::new();
     let mut blocks = HashSet::<Location>::new();
     let mut x_max = 0;
     let mut y_max = 0;
@@ -211,12 +374,10 @@ fn parse_map(path: &str) -> (Map, Position) {
                 MapElement::Block => {
                     blocks.insert(Location { x, y });
                 }
-                MapElement::Character(d) => {
-                    start = Some(Position {
-                        loc: Location { x, y },
-                        dir: d,
-                    })
-                }
+                MapElement::Character(d) => guards.push(Position {
+                    loc: Location { x, y },
+                    dir: d,
+                }),
                 _ => {}
             }
         }
@@ -227,15 +388,14 @@ fn parse_map(path: &str) -> (Map, Position) {
             x_max,
             y_max,
         },
-        start.unwrap(),
+        guards,
     )
 }
 
 fn lines(path: &str) -> impl Iterator<Item = String> {
     let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
-    let lines = reader.lines().map(|x| x.unwrap());
-    lines
+    reader.lines().map(|x| x.unwrap())
 }
 
 struct Row<I: Iterator<Item = char>> {
@@ -0,0 +1,219 @@
+use crate::jump::{Cell, Grid, State, Tails, state};
+use crate::{Direction, Location, Map, MapElement, MapGetter, Position};
+use std::collections::HashSet;
+
+pub struct Search {
+    /// The obstacles that make the guard visit the most cells before leaving, and that count.
+    pub best: (Vec<Location>, usize),
+    /// The fewest obstacles found that trap the guard in a loop.
+    pub trap: Option<Vec<Location>>,
+    /// How many placements were simulated.
+    pub explored: usize,
+    /// How many placements were skipped because they couldn't beat `best`.
+    pub pruned: usize,
+}
+
+struct Searcher<'a> {
+    grid: &'a Grid,
+    start: State,
+    k: usize,
+    /// The number of cells the guard could reach in any number of moves.
+    reachable: usize,
+    search: Search,
+}
+
+/// Tries every way of adding up to `k` obstacles that changes the guard's patrol.
+///
+/// Obstacles only matter once the guard walks into them, so each is placed on the route the
+/// guard takes with the obstacles placed so far, past the point where it hits the last one.
+/// That visits every set of obstacles the guard hits exactly once, in the order it hits them.
+///
+/// Once a loop as small as a placement has been found, the placement is skipped if it can't
+/// beat the longest patrol; until then every placement is simulated, so finding no loop
+/// proves none exists. The last obstacle can't add more cells than the guard enters after
+/// turning at it, if it then leaves without walking into it again; earlier ones can't add
+/// more than the cells the guard could reach.
+pub fn optimize(map: &Map, start: &Position, k: usize) -> Search {
+    let grid = Grid::new(map);
+    let mut searcher = Searcher {
+        grid: &grid,
+        start: state(start),
+        k,
+        reachable: reachable(map, start.loc),
+        search: Search {
+            best: (Vec::new(), 0),
+            trap: None,
+            explored: 0,
+            pruned: 0,
+        },
+    };
+    searcher.explore(&mut Vec::new(), 0);
+    searcher.search
+}
+
+impl Searcher<'_> {
+    fn explore(&mut self, blocks: &mut Vec<Cell>, after: usize) {
+        let (route, looped) = self.grid.route(self.start, blocks);
+        self.search.explored += 1;
+
+        if looped {
+            if self
+                .search
+                .trap
+                .as_ref()
+                .is_none_or(|t| blocks.len() < t.len())
+            {
+                self.search.trap = Some(locations(blocks));
+            }
+        } else if route.len() + 1 > self.search.best.1 {
+            self.search.best = (locations(blocks), route.len() + 1);
+        }
+        if blocks.len() == self.k {
+            return;
+        }
+
+        let last = blocks.len() + 1 == self.k;
+        let mut tails: Option<Tails> = None;
+        for (i, &(cell, before)) in route.iter().enumerate().skip(after) {
+            if self
+                .bound(blocks, &mut tails, last, i, cell, before)
+                .is_some_and(|bound| bound <= self.search.best.1)
+            {
+                self.search.pruned += 1;
+                continue;
+            }
+            blocks.push(cell);
+            self.explore(blocks, i);
+            blocks.pop();
+        }
+    }
+
+    /// The most cells the guard could visit with `cell`, the `i`th cell it first reaches
+    /// after the start, blocked as well as `blocks`, and perhaps more obstacles after that.
+    /// Returns `None` if the placement mustn't be skipped.
+    fn bound(
+        &self,
+        blocks: &[Cell],
+        tails: &mut Option<Tails>,
+        last: bool,
+        i: usize,
+        cell: Cell,
+        before: State,
+    ) -> Option<usize> {
+        if self
+            .search
+            .trap
+            .as_ref()
+            .is_none_or(|t| t.len() > blocks.len() + 1)
+        {
+            return None;
+        }
+        if !last {
+            // Each obstacle takes a cell the guard might otherwise have visited:
+            return Some(self.reachable.saturating_sub(blocks.len() + 1));
+        }
+        let tails = tails.get_or_insert_with(|| self.grid.tails(blocks));
+        let turned = State {
+            cell: before.cell,
+            dir: before.dir.right(),
+        };
+        tails
+            .exit_steps(self.grid, turned, cell)
+            .map(|steps| i + 1 + steps)
+    }
+}
+
+fn locations(cells: &[Cell]) -> Vec<Location> {
+    cells
+        .iter()
+        .map(|&(x, y)| Location {
+            x: x as isize,
+            y: y as isize,
+        })
+        .collect()
+}
+
+/// The number of open cells connected to `start`.
+fn reachable(map: &Map, start: Location) -> usize {
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(loc) = stack.pop() {
+        for dir in [Direction::N, Direction::E, Direction::S, Direction::W] {
+            let next = loc.step(dir);
+            if matches!(map.get(next), Some(MapElement::Space)) && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    seen.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AltMap, PosHistory, parse_map, run};
+
+    /// The longest patrol with up to two obstacles placed anywhere, by simulating them all.
+    fn brute_force(map: &Map, start: &Position) -> usize {
+        let cells: Vec<Location> = (0..=map.y_max)
+            .flat_map(|y| (0..=map.x_max).map(move |x| Location { x, y }))
+            .filter(|&l| l != start.loc && matches!(map.get(l), Some(MapElement::Space)))
+            .collect();
+        let mut best = 0;
+        for (i, &a) in cells.iter().enumerate() {
+            for &b in &cells[i..] {
+                let alt = AltMap::with_blocks(map, HashSet::from([a, b]));
+                let mut history = PosHistory::new();
+                if run(start.clone(), &alt, &mut history).is_none() {
+                    best = best.max(history.map.len());
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_sample() {
+        let (map, guards) = parse_map("sample.txt");
+        let search = optimize(&map, &guards[0], 0);
+        assert_eq!(search.best, (Vec::new(), 41));
+        assert_eq!(search.explored, 1);
+        assert!(search.trap.is_none());
+
+        // One obstacle can only shorten the patrol here. Once the first trap turns up, the
+        // remaining cells are skipped wherever the guard would leave sooner after turning:
+        let search = optimize(&map, &guards[0], 1);
+        assert_eq!(search.trap, Some(vec![Location { x: 3, y: 6 }]));
+        assert_eq!(search.best, (Vec::new(), 41));
+        assert_eq!(search.explored + search.pruned, 41);
+        assert!(search.pruned > 0);
+
+        let search = optimize(&map, &guards[0], 2);
+        assert_eq!(search.best.1, brute_force(&map, &guards[0]));
+        assert!(search.pruned > 0);
+
+        let search = optimize(&map, &guards[0], 3);
+        assert_eq!(search.best.0.len(), 3);
+        assert_eq!(search.best.1, 48);
+    }
+
+    #[test]
+    fn test_no_loop() {
+        // Without obstacles to turn at, a guard needs four new ones to go round in a loop:
+        let map = Map {
+            blocks: HashSet::new(),
+            x_max: 4,
+            y_max: 4,
+        };
+        let start = Position {
+            loc: Location { x: 2, y: 4 },
+            dir: Direction::N,
+        };
+        let search = optimize(&map, &start, 3);
+        assert!(search.trap.is_none());
+        assert_eq!(reachable(&map, start.loc), 25);
+
+        let search = optimize(&map, &start, 4);
+        assert_eq!(search.trap.map(|t| t.len()), Some(4));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{Direction, Location, MapElement, MapGetter, Position};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+impl Turn {
+    fn apply(self, dir: Direction) -> Direction {
+        match self {
+            Turn::Right => dir.right(),
+            Turn::Left => dir.left(),
+            Turn::Reverse => dir.reverse(),
+        }
+    }
+}
+
+/// How a guard turns at each obstacle, cycling through `turns`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policy {
+    turns: Vec<Turn>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            turns: vec![Turn::Right],
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    /// Parses `right`, `left`, `reverse`, or a repeating sequence of turns such as `RRL`,
+    /// with `B` to turn back.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let turns = match s {
+            "right" => vec![Turn::Right],
+            "left" => vec![Turn::Left],
+            "reverse" => vec![Turn::Reverse],
+            _ => s
+                .chars()
+                .map(|ch| match ch.to_ascii_uppercase() {
+                    'R' => Ok(Turn::Right),
+                    'L' => Ok(Turn::Left),
+                    'B' => Ok(Turn::Reverse),
+                    _ => Err(format!("invalid turn {:?} in policy {:?}", ch, s)),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        if turns.is_empty() {
+            return Err("empty policy".into());
+        }
+        Ok(Policy { turns })
+    }
+}
+
+#[derive(Clone)]
+pub struct Guard {
+    pub pos: Position,
+    policy: Policy,
+    /// Where the guard is in its policy's sequence of turns.
+    phase: usize,
+}
+
+impl Guard {
+    pub fn new(pos: Position) -> Self {
+        Guard::with_policy(pos, Policy::default())
+    }
+
+    pub fn with_policy(pos: Position, policy: Policy) -> Self {
+        Guard {
+            pos,
+            policy,
+            phase: 0,
+        }
+    }
+
+    pub fn next_step(&self) -> Location {
+        self.pos.loc.step(self.pos.dir)
+    }
+
+    pub fn next_direction(&self) -> Direction {
+        self.policy.turns[self.phase].apply(self.pos.dir)
+    }
+
+    pub fn turn(&mut self) {
+        self.pos.dir = self.next_direction();
+        self.phase = (self.phase + 1) % self.policy.turns.len();
+    }
+
+    /// Everything that decides where the guard goes next.
+    fn state(&self) -> (Location, Direction, usize) {
+        (self.pos.loc, self.pos.dir, self.phase)
+    }
+}
+
+/// What a guard does when its next step is onto another guard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Collision {
+    /// Stay put until the way is clear
+    Wait,
+    /// Turn as if the other guard were an obstacle
+    Turn,
+    /// Walk through other guards
+    Pass,
+}
+
+pub struct GuardReport {
+    pub start: Position,
+    pub visited: HashSet<Location>,
+    /// The tick on which the guard left the map, if it did.
+    pub exited: Option<usize>,
+}
+
+pub enum Outcome {
+    /// Every guard left the map by this tick.
+    Exited(usize),
+    /// The guards returned to the state they were in at tick `start`, `length` ticks later.
+    Loop { start: usize, length: usize },
+}
+
+pub struct Report {
+    pub guards: Vec<GuardReport>,
+    pub outcome: Outcome,
+}
+
+/// Moves all guards one step per tick until every guard has left the map or the guards as a
+/// whole repeat a state.
+///
+/// Moves are decided from where the guards stand at the start of a tick: a guard can't step
+/// onto a cell another guard stood on when the tick began, even if that guard has moved off
+/// it already, or onto a cell an earlier guard is stepping onto.
+pub fn simulate(map: &dyn MapGetter, mut guards: Vec<Guard>, collision: Collision) -> Report {
+    let mut reports: Vec<GuardReport> = guards
+        .iter()
+        .map(|g| GuardReport {
+            start: g.pos.clone(),
+            visited: HashSet::from([g.pos.loc]),
+            exited: None,
+        })
+        .collect();
+    let mut active = vec![true; guards.len()];
+    let mut seen = HashMap::new();
+    let mut tick = 0;
+
+    loop {
+        if !active.contains(&true) {
+            return Report {
+                guards: reports,
+                outcome: Outcome::Exited(tick),
+            };
+        }
+        let state: Vec<_> = guards
+            .iter()
+            .zip(&active)
+            .map(|(g, &a)| a.then(|| g.state()))
+            .collect();
+        if let Some(start) = seen.insert(state, tick) {
+            return Report {
+                guards: reports,
+                outcome: Outcome::Loop {
+                    start,
+                    length: tick - start,
+                },
+            };
+        }
+
+        let occupied: HashMap<Location, usize> = (0..guards.len())
+            .filter(|&i| active[i])
+            .map(|i| (guards[i].pos.loc, i))
+            .collect();
+        let mut claimed = HashSet::new();
+        for (i, guard) in guards.iter_mut().enumerate() {
+            if !active[i] {
+                continue;
+            }
+            let next = guard.next_step();
+            match map.get(next) {
+                None => {
+                    active[i] = false;
+                    reports[i].exited = Some(tick + 1);
+                }
+                Some(MapElement::Block) => guard.turn(),
+                Some(_) => {
+                    let blocked = collision != Collision::Pass
+                        && (occupied.get(&next).is_some_and(|&j| j != i)
+                            || claimed.contains(&next));
+                    match (blocked, collision) {
+                        (true, Collision::Turn) => guard.turn(),
+                        (true, _) => {}
+                        (false, _) => {
+                            claimed.insert(next);
+                            guard.pos.loc = next;
+                            reports[i].visited.insert(next);
+                        }
+                    }
+                }
+            }
+        }
+        tick += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_map;
+
+    fn guard(x: isize, y: isize, dir: Direction) -> Guard {
+        Guard::new(Position {
+            loc: Location { x, y },
+            dir,
+        })
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!("right".parse(), Ok(Policy::default()));
+        let p: Policy = "RlB".parse().unwrap();
+        assert_eq!(p.turns, vec![Turn::Right, Turn::Left, Turn::Reverse]);
+        assert!("".parse::<Policy>().is_err());
+        assert!("RX".parse::<Policy>().is_err());
+
+        let mut g = Guard::with_policy(guard(0, 0, Direction::N).pos, p);
+        g.turn();
+        assert_eq!(g.pos.dir, Direction::E);
+        g.turn();
+        assert_eq!(g.pos.dir, Direction::N);
+        g.turn();
+        assert_eq!(g.pos.dir, Direction::S);
+        assert_eq!(g.phase, 0);
+    }
+
+    #[test]
+    fn test_single_guard() {
+        let (map, starts) = parse_map("sample.txt");
+        let guards = starts.into_iter().map(Guard::new).collect();
+        let report = simulate(&map, guards, Collision::Wait);
+        assert_eq!(report.guards[0].visited.len(), 41);
+        assert!(matches!(report.outcome, Outcome::Exited(_)));
+    }
+
+    #[test]
+    fn test_collisions() {
+        let (map, _) = parse_map("sample.txt");
+        // Two guards walking towards each other along an empty row:
+        let guards = || vec![guard(0, 2, Direction::E), guard(3, 2, Direction::W)];
+
+        let report = simulate(&map, guards(), Collision::Pass);
+        assert!(matches!(report.outcome, Outcome::Exited(_)));
+        assert_eq!(report.guards[0].visited.len(), 10);
+
+        // They stand face to face forever:
+        let report = simulate(&map, guards(), Collision::Wait);
+        assert!(matches!(report.outcome, Outcome::Loop { length: 1, .. }));
+        assert_eq!(report.guards[0].visited.len(), 2);
+
+        // They turn away from each other and leave by different edges:
+        let report = simulate(&map, guards(), Collision::Turn);
+        assert!(matches!(report.outcome, Outcome::Exited(_)));
+        assert_eq!(report.guards[0].visited.len(), 6);
+        assert_eq!(report.guards[1].visited.len(), 4);
+
+        // A guard can't follow another onto the cell it leaves in the same tick:
+        let guards = vec![guard(0, 2, Direction::E), guard(1, 2, Direction::E)];
+        let report = simulate(&map, guards, Collision::Wait);
+        assert_eq!(report.guards[0].exited, Some(11));
+        assert_eq!(report.guards[1].exited, Some(9));
+    }
+}
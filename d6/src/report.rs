@@ -0,0 +1,134 @@
+use crate::{AltMap, Location, Map, MapElement, MapGetter, PosHistory, Position, run};
+use std::collections::HashMap;
+
+/// How an obstruction at `cell` traps the guard.
+struct Trap {
+    cell: Location,
+    /// The step on which the guard first reaches the cell on its original route.
+    first_reached: usize,
+    /// How many more times the original route crosses the cell. The obstruction stops the
+    /// guard the first time, so those later approaches become unreachable.
+    later_crossings: usize,
+    /// The first position the guard repeats.
+    entry: Position,
+    steps_to_entry: usize,
+    loop_length: usize,
+}
+
+/// Every obstruction that traps the guard, in the order the original route reaches them.
+fn traps(map: &Map, start: &Position) -> Vec<Trap> {
+    let mut route = PosHistory::new();
+    run(start.clone(), map, &mut route);
+
+    // When and how often the route steps onto each cell:
+    let mut first_reached: Vec<(Location, usize)> = Vec::new();
+    let mut crossings: HashMap<Location, usize> = HashMap::new();
+    for (step, pos) in route.trace.iter().enumerate() {
+        if step > 0 && route.trace[step - 1].loc == pos.loc {
+            continue;
+        }
+        let count = crossings.entry(pos.loc).or_default();
+        if *count == 0 {
+            first_reached.push((pos.loc, step));
+        }
+        *count += 1;
+    }
+
+    let mut traps = Vec::new();
+    for (cell, first) in first_reached {
+        if cell == start.loc {
+            continue;
+        }
+        let mut history = PosHistory::new();
+        let Some(entry) = run(start.clone(), &AltMap::new(map, cell), &mut history) else {
+            continue;
+        };
+        let steps_to_entry = history.trace.iter().position(|p| *p == entry).unwrap();
+        traps.push(Trap {
+            cell,
+            first_reached: first,
+            later_crossings: crossings[&cell] - 1,
+            entry,
+            steps_to_entry,
+            loop_length: history.trace.len() - steps_to_entry,
+        });
+    }
+    traps
+}
+
+/// Prints a table of every loop-making obstruction followed by the map, with the guard's
+/// route as `X` and loop-making obstructions as `O`.
+pub fn report(map: &Map, start: &Position) {
+    let traps = traps(map, start);
+    println!(
+        "{:<12} {:>7} {:>7}  {:<12} {:>3} {:>8} {:>8}",
+        "Obstruction", "Reached", "Later", "Loop entry", "Dir", "To entry", "Length"
+    );
+    for trap in &traps {
+        println!(
+            "{:<12} {:>7} {:>7}  {:<12} {:>3} {:>8} {:>8}",
+            format!("({}, {})", trap.cell.x, trap.cell.y),
+            trap.first_reached,
+            trap.later_crossings,
+            format!("({}, {})", trap.entry.loc.x, trap.entry.loc.y),
+            trap.entry.dir.symbol(),
+            trap.steps_to_entry,
+            trap.loop_length
+        );
+    }
+    let cut_off = traps.iter().filter(|t| t.later_crossings > 0).count();
+    println!(
+        "{} loop-making obstructions, {} of which cut off later crossings of the route",
+        traps.len(),
+        cut_off
+    );
+    println!();
+
+    let mut route = PosHistory::new();
+    run(start.clone(), map, &mut route);
+    let traps: Vec<Location> = traps.iter().map(|t| t.cell).collect();
+    for y in 0..=map.y_max {
+        let row: String = (0..=map.x_max)
+            .map(|x| {
+                let loc = Location { x, y };
+                if loc == start.loc {
+                    start.dir.symbol()
+                } else if traps.contains(&loc) {
+                    'O'
+                } else if let Some(MapElement::Block) = map.get(loc) {
+                    '#'
+                } else if route.map.contains_key(&loc) {
+                    'X'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        println!("{}", row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_map;
+
+    #[test]
+    fn test_traps() {
+        let (map, guards) = parse_map("sample.txt");
+        let traps = traps(&map, &guards[0]);
+        let cells: Vec<(isize, isize)> = traps.iter().map(|t| (t.cell.x, t.cell.y)).collect();
+        assert_eq!(cells, vec![(3, 6), (6, 7), (3, 8), (1, 8), (7, 7), (7, 9)]);
+
+        // Blocking the cell left of the start turns the guard back onto its starting
+        // position, so the whole route so far is the loop:
+        let first = &traps[0];
+        assert_eq!(first.first_reached, 22);
+        assert!(first.entry == guards[0]);
+        assert_eq!((first.steps_to_entry, first.loop_length), (0, 22));
+
+        // The route crosses (6, 7) twice, so the obstruction cuts off the second crossing:
+        assert_eq!(traps[1].later_crossings, 1);
+        assert_eq!(traps.iter().filter(|t| t.later_crossings > 0).count(), 1);
+    }
+}
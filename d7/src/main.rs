@@ -1,18 +1,84 @@
-use std::env;
+use clap::Parser;
+use op::{Op, Range};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::ops::ControlFlow;
+
+mod op;
+
+const PART1: [Op; 2] = [Op::Add, Op::Mul];
+const PART2: [Op; 3] = [Op::Add, Op::Mul, Op::Concat];
+
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Also solve with these operators added to + * ||
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    with: Vec<Op>,
+
+    /// Show how each line is solved with all the operators, and list the unsolvable lines
+    #[arg(short = 'e', long)]
+    witness: bool,
+
+    /// With --witness, also count every operator assignment that solves each line
+    #[arg(short, long, requires = "witness")]
+    count: bool,
+}
 
 fn main() {
-    let path = env::args().nth(1).unwrap();
-    let mut sum: u64 = 0;
-    for eq in EquationIter::new(lines(&path)) {
-        println!("{}: {:?}", eq.result, eq.values);
-        if eq.is_solveable() {
-            sum += eq.result;
+    let cli = Cli::parse();
+    let mut extended = PART2.to_vec();
+    for op in cli.with {
+        if !extended.contains(&op) {
+            extended.push(op);
+        }
+    }
+
+    let mut sums = [0u64; 3];
+    let mut unsolvable = Vec::new();
+    for eq in EquationIter::new(lines(&cli.file)) {
+        if !cli.witness {
+            println!("{}: {:?}", eq.result, eq.values);
+        } else if let Some(ops) = eq.witness(&extended) {
+            match cli.count {
+                true => println!(
+                    "{} ({} solutions)",
+                    eq.expression(&ops),
+                    eq.count(&extended)
+                ),
+                false => println!("{}", eq.expression(&ops)),
+            }
+        } else {
+            unsolvable.push(eq.to_string());
+        }
+        for (sum, ops) in sums.iter_mut().zip([&PART1[..], &PART2, &extended]) {
+            if eq.is_solvable(ops) {
+                *sum += eq.result;
+            }
+        }
+    }
+
+    if cli.witness {
+        println!();
+        println!("Unsolvable ({}):", unsolvable.len());
+        for line in unsolvable {
+            println!("  {}", line);
         }
+        println!();
+    }
+    println!("Sum ({}): {}", symbols(&PART1), sums[0]);
+    println!("Sum ({}): {}", symbols(&PART2), sums[1]);
+    if extended.len() > PART2.len() {
+        println!("Sum ({}): {}", symbols(&extended), sums[2]);
     }
+}
 
-    println!("Sum: {}", sum);
+fn symbols(ops: &[Op]) -> String {
+    let ops: Vec<String> = ops.iter().map(|op| op.to_string()).collect();
+    ops.join(" ")
 }
 
 struct Equation {
@@ -21,50 +87,84 @@ struct Equation {
 }
 
 impl Equation {
-    fn is_solveable(&self) -> bool {
-        let limit = 3_usize.pow(self.values.len() as u32 - 1);
-        for ops in 0..limit {
-            if self.try_solution(ops) {
-                return true;
-            }
-        }
-        false
+    fn is_solvable(&self, ops: &[Op]) -> bool {
+        self.witness(ops).is_some()
+    }
+
+    /// The first assignment of `ops` found that solves the equation, one per gap.
+    fn witness(&self, ops: &[Op]) -> Option<Vec<Op>> {
+        let mut witness = None;
+        self.search(ops, |found| {
+            witness = Some(found.iter().rev().copied().collect());
+            ControlFlow::Break(())
+        });
+        witness
     }
 
-    fn try_solution(&self, mut ops: usize) -> bool {
-        let mut values_iter = self.values.iter();
-        let mut result: u64 = *values_iter.next().unwrap();
+    /// The number of distinct assignments of `ops` that solve the equation.
+    fn count(&self, ops: &[Op]) -> u64 {
+        let mut count = 0;
+        self.search(ops, |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        });
+        count
+    }
 
-        for value in values_iter {
-            let op = ops % 3;
-            if op == 0 {
-                result *= *value;
-            } else if op == 1 {
-                result += *value;
-            } else {
-                result = concat(result, *value);
-            }
-            ops = ops / 3;
+    /// Calls `found` with each assignment of `ops` that solves the equation, last gap first.
+    fn search(&self, ops: &[Op], mut found: impl FnMut(&[Op]) -> ControlFlow<()>) {
+        if let Some(last) = self.values.len().checked_sub(1) {
+            let target = (self.result, self.result);
+            let _ = self.solve(ops, last, target, &mut Vec::new(), &mut found);
+        }
+    }
 
-            if result > self.result {
-                return false;
+    /// Finds the ways `ops` can make the first `last + 1` values evaluate, left to right, to
+    /// a value within `target`.
+    ///
+    /// Works backwards from the last value: each operator narrows the range its left side
+    /// must land in, or rules itself out (`*` needs a divisor, `||` a matching suffix).
+    fn solve(
+        &self,
+        ops: &[Op],
+        last: usize,
+        target: Range,
+        chosen: &mut Vec<Op>,
+        found: &mut dyn FnMut(&[Op]) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let value = self.values[last];
+        if last == 0 {
+            if target.0 <= value && value <= target.1 {
+                return found(chosen);
+            }
+            return ControlFlow::Continue(());
+        }
+        for &op in ops {
+            if let Some(left) = op.preimage(target, value) {
+                chosen.push(op);
+                let flow = self.solve(ops, last - 1, left, chosen, found);
+                chosen.pop();
+                flow?;
             }
         }
+        ControlFlow::Continue(())
+    }
 
-        result == self.result
+    /// The equation written out with `ops` between its values, like `3267: 81 + 40 * 27`.
+    fn expression(&self, ops: &[Op]) -> String {
+        let mut s = format!("{}: {}", self.result, self.values[0]);
+        for (op, value) in ops.iter().zip(&self.values[1..]) {
+            s.push_str(&format!(" {} {}", op, value));
+        }
+        s
     }
 }
 
-fn concat(left: u64, right: u64) -> u64 {
-    let mut tmp = right / 10;
-    let mut digits: u32 = 1;
-
-    while tmp > 0 {
-        digits += 1;
-        tmp /= 10;
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+        write!(f, "{}: {}", self.result, values.join(" "))
     }
-
-    left * 10u64.pow(digits) + right
 }
 
 struct EquationIter<I>
@@ -102,9 +202,101 @@ where
         }
     }
 }
+
 fn lines(path: &str) -> impl Iterator<Item = String> {
     let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
-    let lines = reader.lines().map(|x| x.unwrap());
-    lines
+    reader.lines().map(|x| x.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the assignments of `ops` that solve `eq`, trying each going forwards.
+    fn brute_force(eq: &Equation, ops: &[Op]) -> u64 {
+        let gaps = eq.values.len() as u32 - 1;
+        (0..ops.len().pow(gaps))
+            .filter(|&(mut code)| {
+                let mut acc = Some(eq.values[0]);
+                for &v in &eq.values[1..] {
+                    acc = acc.and_then(|a| ops[code % ops.len()].apply(a, v));
+                    code /= ops.len();
+                }
+                acc == Some(eq.result)
+            })
+            .count() as u64
+    }
+
+    #[test]
+    fn test_sample() {
+        let sum = |ops: &[Op]| -> u64 {
+            EquationIter::new(lines("sample"))
+                .filter(|eq| eq.is_solvable(ops))
+                .map(|eq| eq.result)
+                .sum()
+        };
+        assert_eq!(sum(&PART1), 3749);
+        assert_eq!(sum(&PART2), 11387);
+    }
+
+    #[test]
+    fn test_witness() {
+        let eq = Equation {
+            result: 3267,
+            values: vec![81, 40, 27],
+        };
+        let ops = eq.witness(&PART1).unwrap();
+        assert_eq!(eq.expression(&ops), "3267: 81 * 40 + 27");
+        assert_eq!(eq.count(&PART1), 2);
+        assert_eq!(eq.to_string(), "3267: 81 40 27");
+
+        let eq = Equation {
+            result: 7290,
+            values: vec![6, 8, 6, 15],
+        };
+        let ops = eq.witness(&PART2).unwrap();
+        assert_eq!(eq.expression(&ops), "7290: 6 * 8 || 6 * 15");
+        assert_eq!(eq.witness(&PART1), None);
+        assert_eq!(eq.count(&PART1), 0);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // 2^63 * 2 and 2^63 || 1 would wrap around to 0 and 1 respectively:
+        let eq = Equation {
+            result: 0,
+            values: vec![1 << 63, 2],
+        };
+        assert_eq!(eq.count(&PART2), 0);
+        let eq = Equation {
+            result: (1u64 << 63).wrapping_mul(10).wrapping_add(1),
+            values: vec![1 << 63, 1],
+        };
+        assert_eq!(eq.count(&PART2), 0);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let all = [Op::Add, Op::Mul, Op::Concat, Op::Sub, Op::Div, Op::Pow];
+        for result in 0..60 {
+            for values in [
+                vec![2, 3, 4],
+                vec![1, 0, 5],
+                vec![6, 2, 3, 1],
+                vec![0, 2, 2],
+            ] {
+                let eq = Equation { result, values };
+                for ops in [&all[..], &PART2, &all[3..], &all[1..2]] {
+                    assert_eq!(
+                        eq.count(ops),
+                        brute_force(&eq, ops),
+                        "{:?} with {:?}",
+                        (eq.result, &eq.values),
+                        ops
+                    );
+                }
+            }
+        }
+    }
 }
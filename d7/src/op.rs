@@ -0,0 +1,116 @@
+use aoc::num;
+use std::fmt;
+
+/// The values from `.0` to `.1`, inclusive.
+pub type Range = (u64, u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Op {
+    Add,
+    Mul,
+    Concat,
+    Sub,
+    Div,
+    Pow,
+}
+
+impl Op {
+    /// Evaluates `left op right`, or `None` if the result isn't a `u64` (including
+    /// division by zero).
+    pub fn apply(self, left: u64, right: u64) -> Option<u64> {
+        match self {
+            Op::Add => left.checked_add(right),
+            Op::Mul => left.checked_mul(right),
+            Op::Concat => num::concat(left, right),
+            Op::Sub => left.checked_sub(right),
+            Op::Div => left.checked_div(right),
+            Op::Pow => left.checked_pow(right.try_into().ok()?),
+        }
+    }
+
+    /// The values of `left` for which `left op right` lies within `target`, if any.
+    pub fn preimage(self, target: Range, right: u64) -> Option<Range> {
+        let (lo, hi) = target;
+        let range = match self {
+            Op::Add => (lo.saturating_sub(right), hi.checked_sub(right)?),
+            Op::Sub => (lo.checked_add(right)?, hi.saturating_add(right)),
+            Op::Mul if right == 0 => (lo == 0).then_some((0, u64::MAX))?,
+            Op::Mul => (lo.div_ceil(right), hi / right),
+            Op::Div if right == 0 => return None,
+            Op::Div => (
+                lo.checked_mul(right)?,
+                hi.saturating_mul(right).saturating_add(right - 1),
+            ),
+            Op::Concat => {
+                // `apply` fails if the shift overflows, even with nothing to shift:
+                let shift = 10u64.checked_pow(num::digit_count(right))?;
+                let high = hi.checked_sub(right)?;
+                (lo.saturating_sub(right).div_ceil(shift), high / shift)
+            }
+            Op::Pow if right == 0 => ((lo..=hi).contains(&1)).then_some((0, u64::MAX))?,
+            Op::Pow => {
+                let k = u32::try_from(right).ok()?;
+                let low = num::iroot(lo, k);
+                let low = if self.apply(low, right) == Some(lo) {
+                    low
+                } else {
+                    low + 1
+                };
+                (low, num::iroot(hi, k))
+            }
+        };
+        (range.0 <= range.1).then_some(range)
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Op::Add => "+",
+            Op::Mul => "*",
+            Op::Concat => "||",
+            Op::Sub => "-",
+            Op::Div => "/",
+            Op::Pow => "^",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Op; 6] = [Op::Add, Op::Mul, Op::Concat, Op::Sub, Op::Div, Op::Pow];
+
+    #[test]
+    fn test_preimage_matches_apply() {
+        for op in ALL {
+            for right in 0..12 {
+                for target in [(0, 0), (1, 1), (7, 7), (12, 20), (100, 130), (121, 125)] {
+                    let expected: Vec<u64> = (0..2000)
+                        .filter(|&left| {
+                            op.apply(left, right)
+                                .is_some_and(|v| v >= target.0 && v <= target.1)
+                        })
+                        .collect();
+                    let found: Vec<u64> = match op.preimage(target, right) {
+                        Some((lo, hi)) => (lo..=hi.min(1999)).collect(),
+                        None => Vec::new(),
+                    };
+                    assert_eq!(found, expected, "{} {} into {:?}", op, right, target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(Op::Mul.apply(u64::MAX, 2), None);
+        assert_eq!(Op::Concat.apply(u64::MAX / 10, 99), None);
+        assert_eq!(Op::Pow.apply(2, 64), None);
+        let right = 10_000_000_000_000_000_000;
+        assert_eq!(Op::Concat.apply(0, right), None);
+        assert_eq!(Op::Concat.preimage((5, u64::MAX), right), None);
+    }
+}
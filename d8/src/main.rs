@@ -1,28 +1,89 @@
+use aoc::num;
+use clap::Parser;
 use std::collections::{HashMap, HashSet};
-use std::env;
+use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::ops::{Add, Mul, Sub};
 
-fn main() {
-    let path = env::args().nth(1).unwrap();
-    let map = parse_map(&path);
-    let mut antinodes: HashSet<Position> = HashSet::new();
-    find_antinodes(&map, &mut antinodes);
-    println!("Antinodes: {}", antinodes.len());
-}
-
-fn find_antinodes(map: &Map, antinodes: &mut HashSet<Position>) {
-    for (ch, positions) in map.antennas.iter() {
-        println!("{}: {:?}", ch, positions);
-        for i in 0..positions.len() {
-            for j in i + 1..positions.len() {
-                let p1 = positions[i];
-                let p2 = positions[j];
-                find_harmonic_antinodes(map, antinodes, p1, p1 - p2)
+mod render;
+
+#[derive(Parser)]
+struct Cli {
+    /// Input file
+    file: String,
+
+    /// Which antinodes to count
+    #[arg(short, long, value_enum, value_delimiter = ',', default_values = ["single", "harmonic"])]
+    mode: Vec<Mode>,
+
+    /// Draw the map with each mode's antinodes, and break them down by frequency
+    #[arg(short, long)]
+    render: bool,
+
+    /// Only draw and break down the antennas on this frequency
+    #[arg(short, long, requires = "render")]
+    frequency: Option<char>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Mode {
+    /// One antinode beyond each antenna of a pair, twice as far from the other
+    Single,
+    /// Every multiple of the distance between a pair, along their line
+    Harmonic,
+    /// Every grid point on the line through a pair
+    Lattice,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let map = parse_map(&cli.file);
+    if let Some(ch) = cli.frequency
+        && !map.antennas.contains_key(&ch)
+    {
+        return Err(format!("no antennas on frequency {:?}", ch).into());
+    }
+    for mode in cli.mode {
+        if cli.render {
+            print!("{}", render::render(&map, mode, cli.frequency));
+        }
+        let antinodes = find_antinodes(&map, mode);
+        println!("Antinodes ({:?}): {}", mode, antinodes.len());
+    }
+    Ok(())
+}
+
+fn find_antinodes(map: &Map, mode: Mode) -> HashSet<Position> {
+    let mut antinodes = HashSet::new();
+    for positions in map.antennas.values() {
+        antinodes.extend(frequency_antinodes(map, positions, mode));
+    }
+    antinodes
+}
+
+/// The antinodes made by pairs of the antennas at `positions`, all on one frequency.
+fn frequency_antinodes(map: &Map, positions: &[Position], mode: Mode) -> HashSet<Position> {
+    let mut antinodes = HashSet::new();
+    for i in 0..positions.len() {
+        for j in i + 1..positions.len() {
+            let p1 = positions[i];
+            let p2 = positions[j];
+            let delta = p1 - p2;
+            match mode {
+                Mode::Single => {
+                    for t in [p1 + delta, p2 - delta] {
+                        if map.is_valid(t) {
+                            antinodes.insert(t);
+                        }
+                    }
+                }
+                Mode::Harmonic => find_harmonic_antinodes(map, &mut antinodes, p1, delta),
+                Mode::Lattice => find_harmonic_antinodes(map, &mut antinodes, p1, delta.reduce()),
             }
         }
     }
+    antinodes
 }
 
 fn find_harmonic_antinodes(
@@ -53,6 +114,20 @@ struct Position {
     y: isize,
 }
 
+impl Position {
+    /// The shortest step in the same direction that lands on grid points.
+    fn reduce(self) -> Self {
+        let g = num::gcd(self.x.unsigned_abs() as u64, self.y.unsigned_abs() as u64) as isize;
+        match g {
+            0 => self,
+            _ => Position {
+                x: self.x / g,
+                y: self.y / g,
+            },
+        }
+    }
+}
+
 impl Add for Position {
     type Output = Self;
 
@@ -134,6 +209,36 @@ fn parse_map(path: &str) -> Map {
 fn lines(path: &str) -> impl Iterator<Item = String> {
     let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
-    let lines = reader.lines().map(|x| x.unwrap());
-    lines
+    reader.lines().map(|x| x.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let map = parse_map("sample");
+        assert_eq!(find_antinodes(&map, Mode::Single).len(), 14);
+        assert_eq!(find_antinodes(&map, Mode::Harmonic).len(), 34);
+        assert_eq!(find_antinodes(&map, Mode::Lattice).len(), 34);
+    }
+
+    #[test]
+    fn test_lattice() {
+        // Two antennas (2, 4) apart, with grid points on their line between them:
+        let map = Map {
+            x_max: 9,
+            y_max: 9,
+            antennas: HashMap::from([(
+                'a',
+                vec![Position { x: 1, y: 1 }, Position { x: 3, y: 5 }],
+            )]),
+        };
+        assert_eq!(find_antinodes(&map, Mode::Single).len(), 1);
+        assert_eq!(find_antinodes(&map, Mode::Harmonic).len(), 3);
+        assert_eq!(find_antinodes(&map, Mode::Lattice).len(), 5);
+        assert_eq!(Position { x: -4, y: 6 }.reduce(), Position { x: -2, y: 3 });
+        assert_eq!(Position { x: 0, y: -3 }.reduce(), Position { x: 0, y: -1 });
+    }
 }
@@ -0,0 +1,131 @@
+use crate::{Map, Mode, Position, frequency_antinodes};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// How one frequency's antennas contribute to the antinodes.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Breakdown {
+    pub frequency: char,
+    pub antennas: usize,
+    pub antinodes: usize,
+    /// How many of this frequency's antinodes other frequencies make too.
+    pub shared: usize,
+}
+
+/// Each frequency's antinodes, in frequency order.
+fn antinodes_by_frequency(map: &Map, mode: Mode) -> Vec<(char, Vec<Position>)> {
+    let mut frequencies: Vec<(char, Vec<Position>)> = map
+        .antennas
+        .iter()
+        .map(|(&ch, positions)| {
+            let antinodes = frequency_antinodes(map, positions, mode);
+            (ch, antinodes.into_iter().collect())
+        })
+        .collect();
+    frequencies.sort_by_key(|(ch, _)| *ch);
+    frequencies
+}
+
+pub fn breakdown(map: &Map, mode: Mode) -> Vec<Breakdown> {
+    let frequencies = antinodes_by_frequency(map, mode);
+    let mut makers: HashMap<Position, usize> = HashMap::new();
+    for (_, antinodes) in &frequencies {
+        for &p in antinodes {
+            *makers.entry(p).or_default() += 1;
+        }
+    }
+    frequencies
+        .iter()
+        .map(|(ch, antinodes)| Breakdown {
+            frequency: *ch,
+            antennas: map.antennas[ch].len(),
+            antinodes: antinodes.len(),
+            shared: antinodes.iter().filter(|p| makers[p] > 1).count(),
+        })
+        .collect()
+}
+
+/// Draws the map with antennas by frequency and antinodes as `#`, or `*` where an antinode
+/// falls on an antenna, followed by the breakdown by frequency. With `only`, just that
+/// frequency's antennas and antinodes are drawn and broken down.
+pub fn render(map: &Map, mode: Mode, only: Option<char>) -> String {
+    let shown = |ch: char| only.is_none_or(|o| o == ch);
+    let mut antennas: HashMap<Position, char> = HashMap::new();
+    for (&ch, positions) in &map.antennas {
+        if shown(ch) {
+            antennas.extend(positions.iter().map(|&p| (p, ch)));
+        }
+    }
+    let antinodes: HashMap<Position, char> = antinodes_by_frequency(map, mode)
+        .into_iter()
+        .filter(|(ch, _)| shown(*ch))
+        .flat_map(|(ch, antinodes)| antinodes.into_iter().map(move |p| (p, ch)))
+        .collect();
+
+    let mut s = String::new();
+    for y in 0..=map.y_max {
+        for x in 0..=map.x_max {
+            let p = Position { x, y };
+            s.push(match (antennas.get(&p), antinodes.contains_key(&p)) {
+                (Some(_), true) => '*',
+                (Some(&ch), false) => ch,
+                (None, true) => '#',
+                (None, false) => '.',
+            });
+        }
+        s.push('\n');
+    }
+    s.push('\n');
+
+    writeln!(
+        s,
+        "{:<9} {:>8} {:>9} {:>6}",
+        "Frequency", "Antennas", "Antinodes", "Shared"
+    )
+    .unwrap();
+    for row in breakdown(map, mode) {
+        if shown(row.frequency) {
+            writeln!(
+                s,
+                "{:<9} {:>8} {:>9} {:>6}",
+                row.frequency, row.antennas, row.antinodes, row.shared
+            )
+            .unwrap();
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_map;
+
+    #[test]
+    fn test_breakdown() {
+        let map = parse_map("sample");
+        let rows = breakdown(&map, Mode::Single);
+        assert_eq!(rows.iter().map(|r| r.frequency).collect::<String>(), "0A");
+        assert_eq!((rows[0].antennas, rows[0].antinodes), (4, 10));
+        assert_eq!((rows[1].antennas, rows[1].antinodes), (3, 5));
+        // One cell is an antinode of both frequencies:
+        assert_eq!((rows[0].shared, rows[1].shared), (1, 1));
+    }
+
+    #[test]
+    fn test_render() {
+        let map = parse_map("sample");
+        let s = render(&map, Mode::Single, Some('A'));
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines[1], "...#........");
+        assert_eq!(lines[5], "......A.....");
+        assert_eq!(lines[11], "..........#.");
+        assert_eq!(lines[14], "A                3         5      1");
+        assert_eq!(lines.len(), 15);
+
+        // Every antenna in a pair is a harmonic antinode:
+        let s = render(&map, Mode::Harmonic, None);
+        let drawn: String = s.lines().take(12).collect();
+        assert_eq!(drawn.matches('*').count(), 7);
+    }
+}
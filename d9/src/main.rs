@@ -2,13 +2,15 @@ use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::env;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read};
 
 fn main() {
     let path = env::args().nth(1).unwrap();
-    let (mut files, mut slots) = parse_disk_map(&path);
+    let spans = parse_disk_map(&path);
+    println!("Checksum (blocks): {}", compact(&spans));
+    let (mut files, mut slots) = layout(&spans);
     defrag(&mut files, &mut slots);
-    println!("Checksum: {}", files.checksum());
+    println!("Checksum (files): {}", files.checksum());
 }
 
 struct FreeSlots {
@@ -32,7 +34,6 @@ impl FreeSlots {
             .enumerate()
             .filter_map(|(i, bin)| bin.peek().map(|pos| (i + span - 1, pos.0)))
             .min_by_key(|&(_, pos)| pos)
-            .map(|(index, pos)| (index, pos))
     }
 
     fn claim_free(&mut self, bin: usize, span: usize) {
@@ -50,8 +51,7 @@ struct DiskFile {
 
 impl DiskFile {
     fn checksum(&self) -> usize {
-        let result = (0..self.span).map(|i| (i + self.pos) * self.id).sum();
-        result
+        run_checksum(self.id, self.pos, self.span)
     }
 }
 
@@ -84,22 +84,70 @@ impl FileVec {
 
 fn defrag(filevec: &mut FileVec, slots: &mut FreeSlots) {
     for f in filevec.files.iter_mut().rev() {
-        if let Some((bin, pos)) = slots.find_free(f.span) {
-            if pos < f.pos {
-                slots.claim_free(bin, f.span);
-                f.pos = pos;
+        if let Some((bin, pos)) = slots.find_free(f.span)
+            && pos < f.pos
+        {
+            slots.claim_free(bin, f.span);
+            f.pos = pos;
+        }
+    }
+}
+
+/// The checksum of `span` blocks of file `id` starting at block `pos`.
+fn run_checksum(id: usize, pos: usize, span: usize) -> usize {
+    id * (span * pos + span * span.saturating_sub(1) / 2)
+}
+
+/// Moves file blocks one at a time from the end of the disk into the leftmost free block,
+/// returning the checksum of the result.
+///
+/// Works on whole spans from both ends at once rather than on individual blocks: the front
+/// walks the map left to right, filling each gap from the file at the back, until the two
+/// meet.
+fn compact(spans: &[usize]) -> usize {
+    if spans.is_empty() {
+        return 0;
+    }
+    let mut checksum = 0;
+    let mut pos = 0;
+    let mut front = 0;
+    let mut back = (spans.len() - 1) & !1;
+    let mut back_left = spans[back];
+
+    while front <= back {
+        if front == back {
+            checksum += run_checksum(back / 2, pos, back_left);
+            break;
+        }
+        if front % 2 == 0 {
+            checksum += run_checksum(front / 2, pos, spans[front]);
+            pos += spans[front];
+        } else {
+            let mut gap = spans[front];
+            while gap > 0 && back > front {
+                let take = gap.min(back_left);
+                checksum += run_checksum(back / 2, pos, take);
+                pos += take;
+                gap -= take;
+                back_left -= take;
+                if back_left == 0 {
+                    back -= 2;
+                    back_left = spans[back];
+                }
             }
         }
+        front += 1;
     }
+    checksum
 }
 
-fn parse_disk_map(path: &String) -> (FileVec, FreeSlots) {
+/// The files and free slots described by the disk map's `spans`.
+fn layout(spans: &[usize]) -> (FileVec, FreeSlots) {
     let mut files = FileVec::new();
     let mut slots = FreeSlots::new();
     let mut pos = 0;
 
-    for (index, span) in disk_map_values(&path).enumerate() {
-        let span = span as usize;
+    for (index, &span) in spans.iter().enumerate() {
         if index % 2 == 0 {
             files.push(pos, span)
         } else {
@@ -110,9 +158,72 @@ fn parse_disk_map(path: &String) -> (FileVec, FreeSlots) {
     (files, slots)
 }
 
+/// The lengths of the alternating file and free spans in the disk map.
+fn parse_disk_map(path: &str) -> Vec<usize> {
+    disk_map_values(path).map(|v| v as usize).collect()
+}
+
 fn disk_map_values(path: &str) -> impl Iterator<Item = u32> {
     let file = File::open(path).unwrap();
-    file.bytes()
+    BufReader::new(file)
+        .bytes()
         .map(|b| b.unwrap() as char)
         .filter_map(|c| c.to_digit(10))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compacts the disk block by block, the slow way.
+    fn expand_and_compact(spans: &[usize]) -> usize {
+        let mut blocks: Vec<Option<usize>> = Vec::new();
+        for (index, &span) in spans.iter().enumerate() {
+            let id = (index % 2 == 0).then_some(index / 2);
+            blocks.extend(std::iter::repeat_n(id, span));
+        }
+        let (mut i, mut j) = (0, blocks.len());
+        while i < j {
+            if blocks[i].is_some() {
+                i += 1;
+            } else if blocks[j - 1].is_none() {
+                j -= 1;
+            } else {
+                blocks.swap(i, j - 1);
+            }
+        }
+        blocks
+            .iter()
+            .enumerate()
+            .map(|(pos, id)| pos * id.unwrap_or(0))
+            .sum()
+    }
+
+    #[test]
+    fn test_sample() {
+        let spans = parse_disk_map("sample.txt");
+        assert_eq!(compact(&spans), 1928);
+        let (mut files, mut slots) = layout(&spans);
+        defrag(&mut files, &mut slots);
+        assert_eq!(files.checksum(), 2858);
+    }
+
+    #[test]
+    fn test_compact() {
+        for map in [
+            "12345",
+            "1",
+            "10",
+            "90909",
+            "19",
+            "2333133121414131402",
+            "101010",
+            "0",
+        ] {
+            let spans: Vec<usize> = map.bytes().map(|b| (b - b'0') as usize).collect();
+            assert_eq!(compact(&spans), expand_and_compact(&spans), "{}", map);
+        }
+        assert_eq!(compact(&[1, 2, 3, 4, 5]), 60);
+        assert_eq!(compact(&[]), 0);
+    }
+}